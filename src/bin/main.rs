@@ -3,6 +3,7 @@ use plotting::plot::Plot2D;
 use plotting::plot::Linspace;
 use plotting::plot::Line2D;
 use plotting::plot::Color;
+use plotting::plot::{Marker, DrawStyle};
 use plotting::plot::Plot3D;
 use plotting::plot::Surface3D;
 use plotting::window_3d::Window3D;
@@ -19,7 +20,7 @@ pub fn main() {
     let y: Vec<f64> = x.iter().map(|&x| x*x*x).collect();
     let mut plot = Plot2D::plot(Line2D::from((&x,&y)).color(Color::RED)).grid("both");
     let y2: Vec<f64> = x.iter().map(|&x| x*x).collect();
-    let line = Line2D::new(&x,&y2).color(Color::RED).linestyle(".");
+    let line = Line2D::new(&x,&y2).color(Color::RED).marker(Marker::Point).draw(DrawStyle::None);
     //plot.show();
     //Plot2D::plot(line).show();
     //println!("lines are {:?}", plot.get_lines());
@@ -0,0 +1,111 @@
+use ab_glyph::{Font, FontRef, GlyphId, OutlineCurve, PxScale, ScaleFont};
+
+// The font is compiled into the library so text can be drawn without a window
+// or any system fonts, e.g. when rasterizing on a headless machine.
+pub const FONT_DATA: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+pub fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(FONT_DATA).unwrap()
+}
+
+// Width in pixels of a string drawn at the given size.
+pub fn text_width(content: &str, size: f32) -> f32 {
+    let font = font();
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous: Option<GlyphId> = None;
+    for c in content.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            width += scaled.kern(prev, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+// Ascent and descent in pixels for the given size. The descent is negative.
+pub fn vertical_metrics(size: f32) -> (f32, f32) {
+    let font = font();
+    let scaled = font.as_scaled(PxScale::from(size));
+    (scaled.ascent(), scaled.descent())
+}
+
+// Em size of the font for a text size. Text sizes are the distance from the
+// ascent to the descent, like in iced, while SVG and PDF use the em size.
+pub fn em_size(size: f32) -> f32 {
+    let font = font();
+    size*font.units_per_em().unwrap_or(2048.0)/font.height_unscaled()
+}
+
+pub enum Segment {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    Close,
+}
+
+// Outlines of every glyph of a string as path segments in pixel space. The
+// baseline of the first glyph starts at (x, y) and y grows downwards.
+pub fn text_outline(content: &str, size: f32, x: f32, y: f32) -> Vec<Segment> {
+    let font = font();
+    let scaled = font.as_scaled(PxScale::from(size));
+    let h_scale = scaled.h_scale_factor();
+    let v_scale = scaled.v_scale_factor();
+    let mut segments = Vec::new();
+    let mut pen = x;
+    let mut previous: Option<GlyphId> = None;
+    for c in content.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            pen += scaled.kern(prev, id);
+        }
+        if let Some(outline) = font.outline(id) {
+            let map = |p: ab_glyph::Point| (pen+p.x*h_scale, y-p.y*v_scale);
+            let mut last: Option<ab_glyph::Point> = None;
+            for curve in outline.curves.iter() {
+                let start = match curve {
+                    OutlineCurve::Line(a, _) => *a,
+                    OutlineCurve::Quad(a, _, _) => *a,
+                    OutlineCurve::Cubic(a, _, _, _) => *a,
+                };
+                // A new contour starts whenever a curve doesn't continue the previous one
+                if last != Some(start) {
+                    if last.is_some() {
+                        segments.push(Segment::Close);
+                    }
+                    let (sx, sy) = map(start);
+                    segments.push(Segment::MoveTo(sx, sy));
+                }
+                match curve {
+                    OutlineCurve::Line(_, b) => {
+                        let (bx, by) = map(*b);
+                        segments.push(Segment::LineTo(bx, by));
+                        last = Some(*b);
+                    },
+                    OutlineCurve::Quad(_, b, c) => {
+                        let (bx, by) = map(*b);
+                        let (cx, cy) = map(*c);
+                        segments.push(Segment::QuadTo(bx, by, cx, cy));
+                        last = Some(*c);
+                    },
+                    OutlineCurve::Cubic(_, b, c, d) => {
+                        let (bx, by) = map(*b);
+                        let (cx, cy) = map(*c);
+                        let (dx, dy) = map(*d);
+                        segments.push(Segment::CubicTo(bx, by, cx, cy, dx, dy));
+                        last = Some(*d);
+                    },
+                }
+            }
+            if last.is_some() {
+                segments.push(Segment::Close);
+            }
+        }
+        pen += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    segments
+}
@@ -0,0 +1,200 @@
+use crate::plot::Plot2D;
+use crate::scene::Transform2D;
+use crate::svg;
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+// Writes 2D plots as a single HTML file that works offline. The plot is the
+// same SVG as the exported files, the script next to it adds tooltips, zooming
+// and a legend where the lines can be turned on and off.
+
+const STYLE: &str = "
+body { font-family: 'DejaVu Sans', sans-serif; margin: 16px; }
+#figure { position: relative; display: inline-block; }
+#plot { display: block; border: 1px solid #ddd; cursor: crosshair; }
+#tooltip { position: absolute; display: none; pointer-events: none; padding: 4px 6px;
+           background: rgba(255, 255, 255, 0.9); border: 1px solid #888; font-size: 12px;
+           white-space: nowrap; }
+#legend { margin-top: 8px; font-size: 14px; }
+#legend span { margin-right: 16px; cursor: pointer; user-select: none; }
+#legend span.off { opacity: 0.35; }
+#legend i { display: inline-block; width: 12px; height: 12px; margin-right: 4px;
+            vertical-align: middle; }
+";
+
+const SCRIPT: &str = "
+(function() {
+  var svg = document.getElementById('plot');
+  var tooltip = document.getElementById('tooltip');
+  var width = svg.viewBox.baseVal.width, height = svg.viewBox.baseVal.height;
+  var view = [0, 0, width, height];
+  var marker = document.createElementNS('http://www.w3.org/2000/svg', 'circle');
+  marker.setAttribute('r', 4);
+  marker.setAttribute('fill', 'none');
+  marker.setAttribute('stroke-width', 2);
+  marker.style.display = 'none';
+  svg.appendChild(marker);
+
+  function setView() {
+    svg.setAttribute('viewBox', view.join(' '));
+  }
+  function toPlot(event) {
+    var point = svg.createSVGPoint();
+    point.x = event.clientX;
+    point.y = event.clientY;
+    return point.matrixTransform(svg.getScreenCTM().inverse());
+  }
+  function format(value) {
+    return Number(value.toPrecision(6)).toString();
+  }
+
+  // Zoom around the cursor, double click to go back
+  svg.addEventListener('wheel', function(event) {
+    event.preventDefault();
+    var p = toPlot(event);
+    var factor = event.deltaY < 0 ? 0.8 : 1.25;
+    var w = Math.min(view[2]*factor, width), h = Math.min(view[3]*factor, height);
+    var x = p.x-(p.x-view[0])*w/view[2], y = p.y-(p.y-view[1])*h/view[3];
+    view = [Math.min(Math.max(x, 0), width-w), Math.min(Math.max(y, 0), height-h), w, h];
+    setView();
+  });
+  svg.addEventListener('dblclick', function() {
+    view = [0, 0, width, height];
+    setView();
+  });
+
+  // Show the closest data point within a few pixels of the cursor
+  svg.addEventListener('mousemove', function(event) {
+    var p = toPlot(event);
+    var limit = 8*view[2]/svg.clientWidth;
+    var best = null, distance = limit*limit;
+    SERIES.forEach(function(series) {
+      if (series.hidden) return;
+      series.points.forEach(function(point) {
+        var d = (point[0]-p.x)*(point[0]-p.x)+(point[1]-p.y)*(point[1]-p.y);
+        if (d < distance) {
+          distance = d;
+          best = [series, point];
+        }
+      });
+    });
+    if (best === null) {
+      tooltip.style.display = 'none';
+      marker.style.display = 'none';
+      return;
+    }
+    marker.setAttribute('cx', best[1][0]);
+    marker.setAttribute('cy', best[1][1]);
+    marker.setAttribute('stroke', best[0].color);
+    marker.style.display = '';
+    tooltip.textContent = best[0].name+': x = '+format(best[1][2])+', y = '+format(best[1][3]);
+    var box = svg.getBoundingClientRect();
+    tooltip.style.left = (event.clientX-box.left+12)+'px';
+    tooltip.style.top = (event.clientY-box.top+12)+'px';
+    tooltip.style.display = 'block';
+  });
+  svg.addEventListener('mouseleave', function() {
+    tooltip.style.display = 'none';
+    marker.style.display = 'none';
+  });
+
+  // Clicking a legend entry hides or shows its line
+  var legend = document.getElementById('legend');
+  SERIES.forEach(function(series, i) {
+    var entry = document.createElement('span');
+    var swatch = document.createElement('i');
+    swatch.style.background = series.color;
+    entry.appendChild(swatch);
+    entry.appendChild(document.createTextNode(series.name));
+    entry.addEventListener('click', function() {
+      series.hidden = !series.hidden;
+      entry.className = series.hidden ? 'off' : '';
+      var group = document.getElementById('line'+i);
+      if (group) group.style.display = series.hidden ? 'none' : '';
+    });
+    legend.appendChild(entry);
+  });
+})();
+";
+
+// A string as a JavaScript string literal. Also escapes the characters that
+// would end the script element.
+fn js_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '<' | '>' | '&' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// The lines as JavaScript objects. Every point has its position in the SVG
+// followed by its data values.
+fn series(plot: &Plot2D, width: f32, height: f32) -> String {
+    let transform = Transform2D::new(plot, width, height);
+    let area = transform.area();
+    let mut out = String::from("[");
+    for (n, line) in plot.get_lines().iter().enumerate() {
+        let name = match line.get_legend() {
+            Some(legend) => legend.to_owned(),
+            None => format!("line {}", n+1),
+        };
+        let color = line.get_color();
+        write!(out, "{{name: {}, color: \"rgb({},{},{})\", hidden: false, points: [", js_string(&name),
+               (color.0*255.0).round() as u8, (color.1*255.0).round() as u8,
+               (color.2*255.0).round() as u8).unwrap();
+        for &(x, y) in line.get_data().iter() {
+            let (px, py) = transform.point((x, y));
+            // Points outside the plot area are clipped and can't be hovered
+            if !(px >= area.x && px <= area.x+area.width && py >= area.y && py <= area.y+area.height) {
+                continue;
+            }
+            write!(out, "[{:.2},{:.2},{},{}],", px, py, x, y).unwrap();
+        }
+        out.push_str("]},\n");
+    }
+    out.push(']');
+    out
+}
+
+pub fn render(plot: &Plot2D, width: u32, height: u32) -> String {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>{}</style>\n</head>\n<body>\n<div id=\"figure\">",
+             svg::escape(plot.get_title()), STYLE).unwrap();
+    let svg = plot.to_svg_string(width, height);
+    html.push_str(&svg.replacen("<svg ", "<svg id=\"plot\" ", 1));
+    html.push_str("<div id=\"tooltip\"></div>\n</div>\n<div id=\"legend\"></div>\n");
+    writeln!(html, "<script>\nvar SERIES = {};\n{}</script>\n</body>\n</html>",
+             series(plot, width as f32, height as f32), SCRIPT).unwrap();
+    html
+}
+
+pub fn save<P: AsRef<std::path::Path>>(plot: &Plot2D, path: P, width: u32, height: u32) -> io::Result<()> {
+    fs::write(path, render(plot, width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot::*;
+
+    #[test]
+    fn html_is_self_contained() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 2.0]).legend("</script>"));
+        let html = plot.to_html_string(200, 100);
+        assert!(html.contains("<svg id=\"plot\""));
+        assert!(html.contains("<g id=\"line0\">"));
+        assert!(html.contains("[170.00,30.00,1,2]"));
+        assert_eq!(html.matches("</script>").count(), 1);
+        assert!(!html.contains("src="));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::raster;
+
+use std::env;
+use std::fmt::Write;
+use std::io::{self, Write as IoWrite};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Shows rasterized plots directly in terminals that support graphics, using
+// either the Kitty graphics protocol or Sixel.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+// Guesses the protocol from the environment since terminals can't be asked
+// without switching them to raw mode. PLOTTING_INLINE can be set to "kitty",
+// "sixel" or "none" when the guess is wrong.
+pub fn detect() -> Option<Protocol> {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    match var("PLOTTING_INLINE").as_str() {
+        "kitty" => return Some(Protocol::Kitty),
+        "sixel" => return Some(Protocol::Sixel),
+        "none" => return None,
+        _ => (),
+    }
+    // Multiplexers don't pass the images through
+    if env::var_os("TMUX").is_some() || var("TERM").starts_with("screen") {
+        return None;
+    }
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if env::var_os("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty"
+        || program == "WezTerm" || program == "ghostty" || term == "xterm-ghostty" {
+        Some(Protocol::Kitty)
+    } else if term.starts_with("foot") || term.contains("mlterm") || term == "yaft-256color"
+        || program == "iTerm.app" || program == "mintty" {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3)*4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18-6*i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// The image as Kitty graphics commands. The data is split in chunks of at most
+// 4096 bytes as the protocol requires.
+pub fn kitty(rgba: &[u8], width: u32, height: u32) -> String {
+    let data = base64(rgba);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i+1 < chunks.len() {1} else {0};
+        if i == 0 {
+            write!(out, "\x1b_Ga=T,f=32,s={},v={},m={};", width, height, more).unwrap();
+        } else {
+            write!(out, "\x1b_Gm={};", more).unwrap();
+        }
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+    out.push('\n');
+    out
+}
+
+// The image as a Sixel sequence. The colors are reduced to a 6x6x6 color cube,
+// which is enough for the flat colors of the plots.
+pub fn sixel(rgba: &[u8], width: u32, height: u32) -> String {
+    let (width, height) = (width as usize, height as usize);
+    let level = |c: u8| ((c as u32*5+127)/255) as usize;
+    let indices: Vec<usize> = rgba.chunks(4).map(|p| {
+        // Blend transparent pixels with white
+        let blend = |c: u8| (c as u32*p[3] as u32/255+255-p[3] as u32) as u8;
+        level(blend(p[0]))*36+level(blend(p[1]))*6+level(blend(p[2]))
+    }).collect();
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for i in 0..216 {
+        write!(out, "#{};2;{};{};{}", i, i/36*20, i/6%6*20, i%6*20).unwrap();
+    }
+    for band in (0..height).step_by(6) {
+        let rows = (height-band).min(6);
+        let mut colors: Vec<usize> = indices[band*width..(band+rows)*width].to_vec();
+        colors.sort_unstable();
+        colors.dedup();
+        for (n, &color) in colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            write!(out, "#{}", color).unwrap();
+            // Run length encode the columns of the band
+            let mut previous = None;
+            let mut count = 0;
+            for x in 0..=width {
+                let bits = if x < width {
+                    Some((0..rows).filter(|r| indices[(band+r)*width+x] == color)
+                         .fold(0u8, |bits, r| bits | 1 << r))
+                } else {
+                    None
+                };
+                if bits == previous {
+                    count += 1;
+                    continue;
+                }
+                if let Some(bits) = previous {
+                    let c = (63+bits) as char;
+                    if count > 3 {
+                        write!(out, "!{}{}", count, c).unwrap();
+                    } else {
+                        out.extend(std::iter::repeat_n(c, count));
+                    }
+                }
+                previous = bits;
+                count = 1;
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    out
+}
+
+// Writes the image to stdout with the given protocol.
+pub fn show(protocol: Protocol, rgba: &[u8], width: u32, height: u32) -> io::Result<()> {
+    let image = match protocol {
+        Protocol::Kitty => kitty(rgba, width, height),
+        Protocol::Sixel => sixel(rgba, width, height),
+    };
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle.write_all(image.as_bytes())?;
+    handle.flush()
+}
+
+// Shows the image when the terminal supports graphics and saves it as a PNG in
+// the temporary directory otherwise. Every image gets its own file so the ones
+// of earlier plots and other processes are kept.
+pub fn show_or_save(rgba: &[u8], width: u32, height: u32, name: &str) -> io::Result<()> {
+    static SAVED: AtomicUsize = AtomicUsize::new(0);
+    match detect() {
+        Some(protocol) => show(protocol, rgba, width, height),
+        None => {
+            let file = format!("{}-{}-{}.png", name, process::id(), SAVED.fetch_add(1, Ordering::Relaxed));
+            let path = env::temp_dir().join(file);
+            raster::save_png(&raster::from_rgba(rgba, width, height), &path)?;
+            eprintln!("Saved the plot to {}", path.display());
+            Ok(())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_the_last_group() {
+        assert_eq!(base64(b"plot"), "cGxvdA==");
+        assert_eq!(base64(b"plots"), "cGxvdHM=");
+        assert_eq!(base64(b"plots!"), "cGxvdHMh");
+    }
+
+    #[test]
+    fn sixel_encodes_runs_of_a_color() {
+        let white = [255u8; 4*8*2];
+        assert_eq!(sixel(&white, 8, 2).split("#215;2;100;100;100").nth(1).unwrap(),
+                   "#215!8B-\x1b\\\n");
+    }
+}
@@ -13,4 +13,14 @@ pub mod plot;
 pub mod window;
 pub mod window_3d;
 pub mod math;
+pub mod scene;
+pub mod raster;
+pub mod svg;
+pub mod pdf;
+pub mod terminal;
+pub mod inline;
+pub mod html;
+pub mod testing;
+pub mod tikz;
+mod font;
 
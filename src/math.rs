@@ -20,6 +20,20 @@ pub fn create_camera(pos: &Vector3<f32>, target: &Vector3<f32>) -> Matrix4<f32>
 
 }
 
+// Camera angles (elevation, azimuth) in degrees that 3D plots are first shown with
+pub const DEFAULT_ANGLES: (f32, f32) = (47.5, -116.1);
+
+// Direction to the camera for the given (elevation, azimuth) in degrees. This is
+// how the 3D window turns mouse rotations into a camera position.
+pub fn camera_position(angles: (f32, f32)) -> Vector3<f32> {
+    let dir_x = angles.1.to_radians().cos()
+        *angles.1.to_radians().cos();
+    let dir_y = angles.0.to_radians().sin();
+    let dir_z =  angles.1.to_radians().sin()
+        *angles.0.to_radians().cos();
+    Vector3::new(dir_x, dir_y, dir_z).normalize()
+}
+
 pub fn project(camera: &Matrix4<f32>, point: &Vector3<f32>) -> [f32;2] {
     //let hpoint = point;
     let point_3d = camera*point.to_homogeneous();
@@ -28,6 +42,13 @@ pub fn project(camera: &Matrix4<f32>, point: &Vector3<f32>) -> [f32;2] {
     
 }
 
+// Position along the viewing direction of the projection. Larger values are
+// closer to the viewer.
+pub fn depth(camera: &Matrix4<f32>, point: &Vector3<f32>) -> f32 {
+    let point_3d = camera*point.to_homogeneous();
+    point_3d[1]
+}
+
 pub fn project_line(camera: &Matrix4<f32>, point_1: &Vector3<f32>, point_2: &Vector3<f32>) -> [(f32, f32);2] {
     let p1 = project(&camera, &point_1);
     let p2 = project(&camera, &point_2);
@@ -81,18 +102,290 @@ pub fn cartesian_to_polar(points: &Vector3<f32>) -> (f32,f32,f32) {
 pub fn meshgrid(x: &[f32], y: &[f32]) -> (DMatrix<f32>, DMatrix<f32>) {
     let rows = y.len();
     let cols = x.len();
-    let x_iter = x.iter().copied().cycle().take(rows*cols);
-    let y_iter = y.iter().copied().cycle().take(rows*cols);
-    //println!("Test");
-    let mut X = DMatrix::from_iterator(rows, cols, x_iter).transpose();
-    let mut Y = DMatrix::from_iterator(rows, cols, y_iter);
-    //println!("X is {}", X);
-    //println!("Y is {}", Y);
+    // Both have a row for every y and a column for every x
+    let x_grid = DMatrix::from_fn(rows, cols, |_, j| x[j]);
+    let y_grid = DMatrix::from_fn(rows, cols, |i, _| y[i]);
 
+    (x_grid, y_grid)
 
-    (X, Y)
-    
 }
 
+// A crossing of the level on the grid edge from the point (i, j) to the next
+// point along the rows (0) or the columns (1)
+type GridEdge = (usize, usize, usize);
+
+// The lines or polygons of one contour level or band
+pub type Contours = Vec<Vec<(f64, f64)>>;
+
+fn crossing(x: &DMatrix<f32>, y: &DMatrix<f32>, z: &DMatrix<f32>, level: f64, edge: GridEdge) -> (f64, f64) {
+    let (i, j, along) = edge;
+    let (i2, j2) = if along == 0 {(i, j+1)} else {(i+1, j)};
+    let (z1, z2) = (z[(i, j)] as f64, z[(i2, j2)] as f64);
+    let t = (level-z1)/(z2-z1);
+    let lerp = |m: &DMatrix<f32>| m[(i, j)] as f64+(m[(i2, j2)] as f64-m[(i, j)] as f64)*t;
+    (lerp(x), lerp(y))
+}
+
+// The lines where z crosses the level, found with marching squares. Every cell
+// of the grid is cut between the edges where the level lies between the values
+// at the corners, and the pieces are joined into lines. Closed lines end with
+// their first point.
+pub fn contour_lines(x: &DMatrix<f32>, y: &DMatrix<f32>, z: &DMatrix<f32>, level: f64) -> Contours {
+    let (rows, cols) = z.shape();
+    let mut segments: Vec<[GridEdge;2]> = Vec::new();
+    for i in 0..rows.saturating_sub(1) {
+        for j in 0..cols.saturating_sub(1) {
+            let corners = [z[(i, j)], z[(i, j+1)], z[(i+1, j+1)], z[(i+1, j)]];
+            if corners.iter().any(|c| !c.is_finite()) {
+                continue;
+            }
+            let inside: Vec<bool> = corners.iter().map(|&c| c as f64 >= level).collect();
+            // The edges between the corners, in the same order around the cell
+            let edges = [(i, j, 0), (i, j+1, 1), (i+1, j, 0), (i, j, 1)];
+            let cut: Vec<GridEdge> = (0..4).filter(|&k| inside[k] != inside[(k+1)%4]).map(|k| edges[k]).collect();
+            match cut.len() {
+                2 => segments.push([cut[0], cut[1]]),
+                4 => {
+                    // A saddle, the value at the center decides which corners are connected
+                    let center = corners.iter().map(|&c| c as f64).sum::<f64>()/4.0 >= level;
+                    if center == inside[0] {
+                        segments.push([cut[0], cut[1]]);
+                        segments.push([cut[2], cut[3]]);
+                    } else {
+                        segments.push([cut[3], cut[0]]);
+                        segments.push([cut[1], cut[2]]);
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    let mut touching: std::collections::BTreeMap<GridEdge, Vec<usize>> = std::collections::BTreeMap::new();
+    for (n, segment) in segments.iter().enumerate() {
+        for edge in segment.iter() {
+            touching.entry(*edge).or_default().push(n);
+        }
+    }
+    // Open lines have to start at one of their ends, closed ones can start anywhere
+    let starts: Vec<GridEdge> = touching.iter().filter(|(_, s)| s.len() == 1).map(|(e, _)| *e)
+        .chain(segments.iter().map(|s| s[0])).collect();
+    let mut used = vec![false; segments.len()];
+    let mut lines = Vec::new();
+    for start in starts {
+        let mut current = start;
+        let mut line = vec![crossing(x, y, z, level, current)];
+        while let Some(&n) = touching[&current].iter().find(|&&n| !used[n]) {
+            used[n] = true;
+            current = if segments[n][0] == current {segments[n][1]} else {segments[n][0]};
+            line.push(crossing(x, y, z, level, current));
+        }
+        if line.len() > 1 {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+// The parts of every grid cell where z lies between low and high, for filled
+// contours. The cells are clipped along their edges, where z is linear, so the
+// borders meet the lines of contour_lines.
+pub fn contour_bands(x: &DMatrix<f32>, y: &DMatrix<f32>, z: &DMatrix<f32>, low: f64, high: f64) -> Contours {
+    // Keeps the part of the polygon where the value is on the inner side of the limit
+    fn clip(polygon: &[(f64, f64, f64)], limit: f64, above: bool) -> Vec<(f64, f64, f64)> {
+        let inner = |p: &(f64, f64, f64)| if above {p.2 >= limit} else {p.2 <= limit};
+        let mut clipped = Vec::new();
+        for k in 0..polygon.len() {
+            let (a, b) = (polygon[k], polygon[(k+1)%polygon.len()]);
+            if inner(&a) {
+                clipped.push(a);
+            }
+            if inner(&a) != inner(&b) {
+                let t = (limit-a.2)/(b.2-a.2);
+                clipped.push((a.0+(b.0-a.0)*t, a.1+(b.1-a.1)*t, limit));
+            }
+        }
+        clipped
+    }
+
+    let (rows, cols) = z.shape();
+    let mut polygons = Vec::new();
+    for i in 0..rows.saturating_sub(1) {
+        for j in 0..cols.saturating_sub(1) {
+            let cell: Vec<(f64, f64, f64)> = [(i, j), (i, j+1), (i+1, j+1), (i+1, j)].iter()
+                .map(|&p| (x[p] as f64, y[p] as f64, z[p] as f64)).collect();
+            if cell.iter().any(|p| !p.2.is_finite()) {
+                continue;
+            }
+            let band = clip(&clip(&cell, low, true), high, false);
+            if band.len() >= 3 {
+                polygons.push(band.into_iter().map(|p| (p.0, p.1)).collect());
+            }
+        }
+    }
+    polygons
+}
+
+// Bilinear interpolation of m at p, on a grid like the one from meshgrid. None
+// outside the grid.
+fn interpolate(xs: &[f64], ys: &[f64], m: &DMatrix<f32>, p: (f64, f64)) -> Option<f64> {
+    let cell = |axis: &[f64], value: f64| {
+        let n = axis.len();
+        if n < 2 || value < axis[0].min(axis[n-1]) || value > axis[0].max(axis[n-1]) {
+            return None;
+        }
+        // Axes may go either way
+        let k = if axis[0] <= axis[n-1] {
+            axis.partition_point(|&a| a <= value)
+        } else {
+            axis.partition_point(|&a| a >= value)
+        }.clamp(1, n-1)-1;
+        Some((k, (value-axis[k])/(axis[k+1]-axis[k])))
+    };
+    let ((j, tx), (i, ty)) = (cell(xs, p.0)?, cell(ys, p.1)?);
+    let at = |i: usize, j: usize| m[(i, j)] as f64;
+    let value = (at(i, j)*(1.0-tx)+at(i, j+1)*tx)*(1.0-ty)+(at(i+1, j)*(1.0-tx)+at(i+1, j+1)*tx)*ty;
+    if value.is_finite() {Some(value)} else {None}
+}
+
+// Streamlines of the field (u, v) over the grid of x and y. They start from
+// seeds spread over the grid and follow the direction of the field both ways
+// with second order Runge-Kutta steps. A line ends where it leaves the grid,
+// the field vanishes, it comes close to another line or it comes back to where
+// it has been, like around a vortex. Higher densities give
+// more and closer lines. Every line goes along the direction of the field.
+pub fn streamlines(x: &DMatrix<f32>, y: &DMatrix<f32>, u: &DMatrix<f32>, v: &DMatrix<f32>,
+                   density: f64) -> Vec<Vec<(f64, f64)>> {
+    let xs: Vec<f64> = x.row(0).iter().map(|&v| v as f64).collect();
+    let ys: Vec<f64> = y.column(0).iter().map(|&v| v as f64).collect();
+    if xs.len() < 2 || ys.len() < 2 {
+        return Vec::new();
+    }
+    let (x0, x1, y0, y1) = (xs[0], xs[xs.len()-1], ys[0], ys[ys.len()-1]);
+    // Lines mark the cells of a coarse grid they pass, and stop in marked cells
+    let cells = ((25.0*density).round() as usize).max(2);
+    let mut taken = vec![None; cells*cells];
+    let cell = |p: (f64, f64)| {
+        let i = (((p.1-y0)/(y1-y0)*cells as f64) as usize).min(cells-1);
+        let j = (((p.0-x0)/(x1-x0)*cells as f64) as usize).min(cells-1);
+        i*cells+j
+    };
+    let step = (x1-x0).abs().min((y1-y0).abs())/cells as f64*0.2;
+    // The direction of the field scaled to a step, backwards when sign is -1
+    let direction = |p: (f64, f64), sign: f64| {
+        let (du, dv) = (interpolate(&xs, &ys, u, p)?, interpolate(&xs, &ys, v, p)?);
+        let speed = (du*du+dv*dv).sqrt();
+        if speed < 1e-12 {
+            return None;
+        }
+        Some((sign*du/speed*step, sign*dv/speed*step))
+    };
+
+    let mut lines = Vec::new();
+    for seed in 0..cells*cells {
+        if taken[seed].is_some() {
+            continue;
+        }
+        let start = (x0+((seed%cells) as f64+0.5)*(x1-x0)/cells as f64,
+                     y0+((seed/cells) as f64+0.5)*(y1-y0)/cells as f64);
+        let id = lines.len();
+        let mut halves = Vec::new();
+        for &sign in [-1.0, 1.0].iter() {
+            let mut half = Vec::new();
+            let mut p = start;
+            let mut current = cell(start);
+            for _ in 0..(cells*40) {
+                let d1 = match direction(p, sign) { Some(d) => d, None => break };
+                let d2 = match direction((p.0+d1.0*0.5, p.1+d1.1*0.5), sign) { Some(d) => d, None => break };
+                let next = (p.0+d2.0, p.1+d2.1);
+                if next.0 < x0.min(x1) || next.0 > x0.max(x1) || next.1 < y0.min(y1) || next.1 > y0.max(y1) {
+                    break;
+                }
+                // Other lines' cells and the line's own earlier cells are both taken
+                let c = cell(next);
+                if taken[c].is_some_and(|other| other != id || c != current) {
+                    break;
+                }
+                taken[c] = Some(id);
+                current = c;
+                half.push(next);
+                p = next;
+            }
+            halves.push(half);
+        }
+        let mut line: Vec<(f64, f64)> = halves[0].iter().rev().copied().collect();
+        line.push(start);
+        line.extend(halves[1].iter().copied());
+        taken[seed] = Some(id);
+        // Short pieces only clutter the plot
+        if line.len() > 5 {
+            lines.push(line);
+        } else {
+            for &p in line.iter() {
+                if taken[cell(p)] == Some(id) {
+                    taken[cell(p)] = None;
+                }
+            }
+            taken[seed] = Some(usize::MAX);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::Plot3D;
 
+    #[test]
+    fn meshgrid_has_a_row_per_y_and_a_column_per_x() {
+        let (x, y) = meshgrid(&[0.0, 1.0, 2.0], &[5.0, 6.0]);
+        assert_eq!(x.shape(), (2, 3));
+        assert_eq!(y.shape(), (2, 3));
+        assert_eq!((x[(1, 2)], y[(1, 2)]), (2.0, 6.0));
+        // Surfaces read x along the first row and y down the first column
+        let z = x.zip_map(&y, |a, b| a+b);
+        let plot = Plot3D::plot((x, y, z).into());
+        assert_eq!(plot.get_axes().get_axes().get_xaxes(), [0.0, 2.0]);
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [5.0, 6.0]);
+        plot.scene(200.0, 100.0, DEFAULT_ANGLES);
+    }
+
+    #[test]
+    fn contours_go_around_a_peak() {
+        let (x, y) = meshgrid(&[-1.0, 0.0, 1.0], &[-1.0, 0.0, 1.0]);
+        let z = DMatrix::from_row_slice(3, 3, &[0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0]);
+        let lines = contour_lines(&x, &y, &z, 1.0);
+        // One closed diamond through the middle of the edges around the peak
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].len(), 5);
+        assert_eq!(lines[0].first(), lines[0].last());
+        assert!(lines[0].iter().all(|p| (p.0.abs()+p.1.abs()-0.5).abs() < 1e-9));
+        assert_eq!(contour_bands(&x, &y, &z, 1.0, 2.0).len(), 4);
+    }
 
+    #[test]
+    fn streamlines_follow_the_field() {
+        let (x, y) = meshgrid(&[0.0, 1.0, 2.0], &[0.0, 1.0, 2.0]);
+        let u = DMatrix::from_element(3, 3, 2.0);
+        let v = DMatrix::from_element(3, 3, 0.0);
+        let lines = streamlines(&x, &y, &u, &v, 1.0);
+        assert!(!lines.is_empty());
+        // Every line goes along the field, from left to right at a fixed height
+        for line in lines {
+            assert!(line.windows(2).all(|p| p[1].0 > p[0].0 && (p[1].1-p[0].1).abs() < 1e-9));
+        }
+
+        // The same field on a grid that goes the other way
+        let (x, y) = meshgrid(&[2.0, 1.0, 0.0], &[2.0, 1.0, 0.0]);
+        assert!(!streamlines(&x, &y, &u, &v, 1.0).is_empty());
+
+        // Around a vortex a line ends after one turn of about 22 steps, long
+        // before the limit of 80 steps each way on the 2 by 2 grid of seeds
+        let axis: Vec<f32> = (0..11).map(|k| k as f32*0.2-1.0).collect();
+        let (x, y) = meshgrid(&axis, &axis);
+        let lines = streamlines(&x, &y, &-&y, &x, 0.08);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].len() < 30);
+    }
+}
@@ -0,0 +1,284 @@
+use ab_glyph::{Font, GlyphId};
+use crate::font;
+use crate::plot::Color;
+use crate::scene::{self, Scene, Primitive, Segment, HAlign, VAlign};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+// Writes scenes as single page PDF documents. Everything is drawn as vectors and
+// the font is embedded, so the text looks the same in every viewer.
+
+// Scenes are laid out in CSS pixels, 96 per inch, while PDF uses points.
+const POINTS_PER_PIXEL: f32 = 72.0/96.0;
+
+// Physical size of a PDF page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    // Size in points
+    width: f32,
+    height: f32,
+}
+
+impl PageSize {
+    pub fn inches(width: f32, height: f32) -> Self {
+        Self{width: width*72.0, height: height*72.0}
+    }
+
+    pub fn mm(width: f32, height: f32) -> Self {
+        Self::inches(width/25.4, height/25.4)
+    }
+
+    // Size of the scene that fills the page.
+    pub fn pixels(&self) -> (f32, f32) {
+        (self.width/POINTS_PER_PIXEL, self.height/POINTS_PER_PIXEL)
+    }
+}
+
+fn color(color: &Color) -> String {
+    format!("{:.3} {:.3} {:.3}", color.0, color.1, color.2)
+}
+
+fn path_data(content: &mut String, path: &scene::Path) {
+    for segment in path.segments.iter() {
+        match *segment {
+            Segment::MoveTo(x, y) => writeln!(content, "{:.2} {:.2} m", x, y),
+            Segment::LineTo(x, y) => writeln!(content, "{:.2} {:.2} l", x, y),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => {
+                writeln!(content, "{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c", x1, y1, x2, y2, x, y)
+            },
+            Segment::Close => writeln!(content, "h"),
+        }.unwrap();
+    }
+}
+
+struct Writer {
+    content: String,
+    // Glyphs used by the text and the character each one shows
+    glyphs: BTreeMap<u16, char>,
+    // Opacities used by the colors, each one gets a graphics state
+    alphas: Vec<f32>,
+}
+
+impl Writer {
+    // The same graphics state sets both the stroke and the fill opacity.
+    fn alpha(&mut self, alpha: f32) {
+        if alpha >= 1.0 {
+            return;
+        }
+        let index = match self.alphas.iter().position(|a| *a == alpha) {
+            Some(index) => index,
+            None => {
+                self.alphas.push(alpha);
+                self.alphas.len()-1
+            },
+        };
+        writeln!(self.content, "/GS{} gs", index).unwrap();
+    }
+
+    fn text(&mut self, text: &scene::Text) {
+        let font = font::font();
+        let scale = 1000.0/font.units_per_em().unwrap_or(2048.0);
+        let width = font::text_width(&text.content, text.size);
+        let (ascent, descent) = font::vertical_metrics(text.size);
+        let x = match text.horizontal_alignment {
+            HAlign::Left => text.position.0,
+            HAlign::Center => text.position.0-width*0.5,
+            HAlign::Right => text.position.0-width,
+        };
+        let y = match text.vertical_alignment {
+            VAlign::Top => text.position.1+ascent,
+            VAlign::Center => text.position.1+(ascent+descent)*0.5,
+            VAlign::Bottom => text.position.1+descent,
+        };
+        // Glyph ids with the kerning between them, in thousandths of the em size
+        let mut glyphs = String::new();
+        let mut previous: Option<GlyphId> = None;
+        for c in text.content.chars() {
+            let id = font.glyph_id(c);
+            if let Some(prev) = previous {
+                let kern = font.kern_unscaled(prev, id);
+                if kern != 0.0 {
+                    write!(glyphs, "> {:.1} <", -kern*scale).unwrap();
+                }
+            }
+            write!(glyphs, "{:04X}", id.0).unwrap();
+            self.glyphs.entry(id.0).or_insert(c);
+            previous = Some(id);
+        }
+        writeln!(self.content, "q").unwrap();
+        self.alpha(text.color.3);
+        // Flip the text matrix back so the glyphs are upright
+        writeln!(self.content, "{} rg BT /F1 {:.2} Tf 1 0 0 -1 {:.2} {:.2} Tm [<{}>] TJ ET Q",
+                 color(&text.color), font::em_size(text.size), x, y, glyphs).unwrap();
+    }
+
+    fn primitives(&mut self, primitives: &[Primitive]) {
+        for primitive in primitives {
+            match primitive {
+                Primitive::Stroke{path, color: c, width} => {
+                    writeln!(self.content, "q").unwrap();
+                    self.alpha(c.3);
+                    writeln!(self.content, "{} RG {} w 0 J 0 j", color(c), width).unwrap();
+                    path_data(&mut self.content, path);
+                    writeln!(self.content, "S Q").unwrap();
+                },
+                Primitive::Fill{path, color: c} => {
+                    writeln!(self.content, "q").unwrap();
+                    self.alpha(c.3);
+                    writeln!(self.content, "{} rg", color(c)).unwrap();
+                    path_data(&mut self.content, path);
+                    writeln!(self.content, "f Q").unwrap();
+                },
+                Primitive::Text(text) => self.text(text),
+                Primitive::Clip{rect, primitives} => {
+                    writeln!(self.content, "q {} {} {} {} re W n", rect.x, rect.y, rect.width,
+                             rect.height).unwrap();
+                    self.primitives(primitives);
+                    writeln!(self.content, "Q").unwrap();
+                },
+                Primitive::Group{primitives, ..} => self.primitives(primitives),
+            }
+        }
+    }
+}
+
+fn stream(dictionary: &str, data: &[u8]) -> Vec<u8> {
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+    let mut object = format!("<< {} /Filter /FlateDecode /Length {} >>\nstream\n", dictionary,
+                             compressed.len()).into_bytes();
+    object.extend_from_slice(&compressed);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+// The box around every glyph of the font in font units, [x_min, y_min, x_max,
+// y_max] from the head table, together with the units per em
+fn font_bbox(data: &[u8]) -> Option<([i16;4], u16)> {
+    let u16_at = |i: usize| data.get(i..i+2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let u32_at = |i: usize| data.get(i..i+4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let tables = u16_at(4)? as usize;
+    let head = (0..tables).map(|k| 12+16*k).find(|&record| data.get(record..record+4) == Some(b"head"))?;
+    let offset = u32_at(head+8)? as usize;
+    let coordinate = |i: usize| u16_at(offset+i).map(|v| v as i16);
+    Some(([coordinate(36)?, coordinate(38)?, coordinate(40)?, coordinate(42)?], u16_at(offset+18)?))
+}
+
+// Character map so text copied from the document gives back the original characters.
+fn to_unicode(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from("/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+        /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+        /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+        1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n");
+    let entries: Vec<(&u16, &char)> = glyphs.iter().collect();
+    for chunk in entries.chunks(100) {
+        writeln!(cmap, "{} beginbfchar", chunk.len()).unwrap();
+        for (id, c) in chunk {
+            let mut units = [0u16; 2];
+            let hex: String = c.encode_utf16(&mut units).iter().map(|u| format!("{:04X}", u)).collect();
+            writeln!(cmap, "<{:04X}> <{}>", id, hex).unwrap();
+        }
+        writeln!(cmap, "endbfchar").unwrap();
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+pub fn render(scene: &Scene) -> Vec<u8> {
+    let font = font::font();
+    let scale = 1000.0/font.units_per_em().unwrap_or(2048.0);
+    let width = scene.width*POINTS_PER_PIXEL;
+    let height = scene.height*POINTS_PER_PIXEL;
+
+    // Draw in pixels with y pointing down like the other backends
+    let mut writer = Writer{content: String::new(), glyphs: BTreeMap::new(), alphas: Vec::new()};
+    writeln!(writer.content, "{p} 0 0 -{p} 0 {h} cm", p = POINTS_PER_PIXEL, h = height).unwrap();
+    writeln!(writer.content, "q").unwrap();
+    writer.alpha(scene.background.3);
+    writeln!(writer.content, "{} rg 0 0 {} {} re f Q", color(&scene.background), scene.width,
+             scene.height).unwrap();
+    writer.primitives(&scene.primitives);
+
+    let mut states = String::new();
+    for (i, alpha) in writer.alphas.iter().enumerate() {
+        write!(states, "/GS{} << /ca {} /CA {} >> ", i, alpha, alpha).unwrap();
+    }
+    let mut widths = String::new();
+    for id in writer.glyphs.keys() {
+        write!(widths, "{} [{:.0}] ", id, font.h_advance_unscaled(GlyphId(*id))*scale).unwrap();
+    }
+    // The whole font is embedded so the box has to cover all of its glyphs
+    let (bbox, units_per_em) = font_bbox(font::FONT_DATA).expect("The font has no head table");
+    let bounds: Vec<f32> = bbox.iter().map(|&v| v as f32*1000.0/units_per_em as f32).collect();
+
+    let objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /Font << /F1 5 0 R >> /ExtGState << {}>> >> /Contents 4 0 R >>",
+                width, height, states).into_bytes(),
+        stream("", writer.content.as_bytes()),
+        b"<< /Type /Font /Subtype /Type0 /BaseFont /DejaVuSans /Encoding /Identity-H \
+           /DescendantFonts [6 0 R] /ToUnicode 9 0 R >>".to_vec(),
+        format!("<< /Type /Font /Subtype /CIDFontType2 /BaseFont /DejaVuSans \
+                 /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                 /FontDescriptor 7 0 R /W [{}] /CIDToGIDMap /Identity >>", widths).into_bytes(),
+        format!("<< /Type /FontDescriptor /FontName /DejaVuSans /Flags 32 \
+                 /FontBBox [{:.0} {:.0} {:.0} {:.0}] /ItalicAngle 0 /Ascent {:.0} /Descent {:.0} \
+                 /CapHeight {:.0} /StemV 80 /FontFile2 8 0 R >>",
+                bounds[0], bounds[1], bounds[2], bounds[3], font.ascent_unscaled()*scale,
+                font.descent_unscaled()*scale, font.ascent_unscaled()*scale).into_bytes(),
+        stream(&format!("/Length1 {}", font::FONT_DATA.len()), font::FONT_DATA),
+        stream("", to_unicode(&writer.glyphs).as_bytes()),
+    ];
+
+    let mut pdf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i+1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len()+1);
+    for offset in offsets {
+        writeln!(table, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(table, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len()+1,
+           xref).unwrap();
+    pdf.extend_from_slice(table.as_bytes());
+    pdf
+}
+
+pub fn save<P: AsRef<std::path::Path>>(scene: &Scene, path: P) -> io::Result<()> {
+    fs::write(path, render(scene))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_size_is_in_points() {
+        let page = PageSize::inches(6.0, 4.0);
+        assert_eq!(page.pixels(), (576.0, 384.0));
+        let (width, height) = PageSize::mm(152.4, 101.6).pixels();
+        assert!((width-576.0).abs() < 1e-3 && (height-384.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn the_font_is_compressed_with_its_full_bbox() {
+        let pdf = render(&Scene::new(100.0, 100.0));
+        // The embedded font is deflated, it is most of the file
+        assert!(pdf.len() < font::FONT_DATA.len()*6/10);
+        let text = String::from_utf8_lossy(&pdf);
+        let (bbox, units_per_em) = font_bbox(font::FONT_DATA).unwrap();
+        assert_eq!(units_per_em, 2048);
+        // Nothing is written but the box still covers the widest glyphs of the font
+        assert!(bbox[0] < 0 && bbox[2] > 2048);
+        assert!(text.contains(&format!("/FontBBox [{:.0} ", bbox[0] as f32*1000.0/2048.0)));
+    }
+}
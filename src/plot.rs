@@ -1,16 +1,32 @@
-use crate::window::{Window};
+use crate::window::{Window, Shown};
 use crate::window_3d::Window3D;
 use crate::window::Message;
 use crate::math::*;
+use crate::scene::{self, Scene};
+use crate::raster;
+use crate::svg;
+use crate::pdf::{self, PageSize};
+use crate::terminal;
+use crate::inline;
+use crate::html;
+use crate::tikz;
 use iced::Settings;
 use iced::window;
 use iced::Application;
 use nalgebra::base::{Matrix, DMatrix};
 use std::thread;
+use std::io;
+use std::path::Path;
 
 
 
-#[derive(Debug)]
+// Size in pixels of the plots shown in the terminal
+const INLINE_SIZE: (u32, u32) = (800, 600);
+
+// Size in pixels of the frames of animations
+const TURNTABLE_SIZE: (u32, u32) = (640, 480);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color(pub f32, pub f32, pub f32, pub f32);
 
 
@@ -20,6 +36,119 @@ impl Color {
     pub const RED: Color = Color(1.0, 0.0, 0.0, 1.0);
     pub const BLUE: Color = Color(0.0, 0.0, 1.0, 1.0);
 
+    // The same color with the opacity multiplied by alpha
+    pub fn scale_alpha(self, alpha: f32) -> Self {
+        Color(self.0, self.1, self.2, self.3*alpha)
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    // The small dot of the "." linestyle
+    Point,
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    Cross,
+    Plus,
+    Star,
+}
+
+impl Marker {
+    // Markers that are only outlines, they have no face to fill
+    pub fn is_open(&self) -> bool {
+        matches!(self, Marker::Cross | Marker::Plus)
+    }
+}
+
+// Splits a linestyle like "o-" into its marker and whether the points are
+// connected by a line. The marker characters are . o s ^ D x + *
+pub fn parse_linestyle(linestyle: &str) -> Result<(Option<Marker>, bool), String> {
+    let invalid = || format!("Not a valid linestyle: {:?}", linestyle);
+    let mut marker = None;
+    let mut line = false;
+    for c in linestyle.chars() {
+        let m = match c {
+            '-' if !line => {
+                line = true;
+                continue;
+            },
+            '.' => Marker::Point,
+            'o' => Marker::Circle,
+            's' => Marker::Square,
+            '^' => Marker::Triangle,
+            'D' => Marker::Diamond,
+            'x' => Marker::Cross,
+            '+' => Marker::Plus,
+            '*' => Marker::Star,
+            _ => return Err(invalid()),
+        };
+        if marker.is_some() {
+            return Err(invalid());
+        }
+        marker = Some(m);
+    }
+    if marker.is_none() && !line {
+        return Err(invalid());
+    }
+    Ok((marker, line))
+}
+
+// A straight line from the first point to the second
+pub type Segment2D = [(f64, f64);2];
+
+// How the y errors of a line are drawn, as bars with caps or as a shaded band
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorStyle {
+    Bars,
+    Band,
+}
+
+// Where a step line moves to the next value: at the next point (Pre), at the
+// point itself (Post) or halfway between them (Mid)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    Pre,
+    Post,
+    Mid,
+}
+
+// How the points of a line are joined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawStyle {
+    // Only the markers
+    None,
+    Line,
+    Steps(Step),
+    // A vertical line from the baseline up to every point
+    Stem(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    pub marker: Option<Marker>,
+    pub draw: DrawStyle,
+}
+
+impl LineStyle {
+    // The style of a linestyle like "o-", see parse_linestyle
+    pub fn parse(linestyle: &str) -> Result<Self, String> {
+        let (marker, line) = parse_linestyle(linestyle)?;
+        Ok(Self {
+            marker,
+            draw: if line {DrawStyle::Line} else {DrawStyle::None},
+        })
+    }
+
+    // Whether the points are joined one after the other
+    pub fn is_connected(&self) -> bool {
+        match self.draw {
+            DrawStyle::Line | DrawStyle::Steps(_) => true,
+            DrawStyle::None | DrawStyle::Stem(_) => false,
+        }
+    }
 }
 
 pub fn min<T: Into<f64>+Copy>(vals: &[T]) -> (f64, usize) {
@@ -37,6 +166,94 @@ pub fn min<T: Into<f64>+Copy>(vals: &[T]) -> (f64, usize) {
     return (min, pos)
 }
 
+// Value at the fraction p of sorted values, interpolating between neighbours
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let position = p*(sorted.len()-1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    sorted[below]+(sorted[above]-sorted[below])*(position-below as f64)
+}
+
+// Gaussian kernel density estimate of the values at the given points. The
+// bandwidth defaults to Scott's rule.
+pub fn kde(vals: &[f64], points: &[f64], bandwidth: Option<f64>) -> Vec<f64> {
+    let n = vals.len() as f64;
+    let bandwidth = bandwidth.unwrap_or_else(|| {
+        let mean = vals.iter().sum::<f64>()/n;
+        let deviation = (vals.iter().map(|v| (v-mean).powi(2)).sum::<f64>()/n).sqrt();
+        // Equal values would give no width at all
+        if deviation > 0.0 { deviation*n.powf(-0.2) } else { 1.0 }
+    });
+    let scale = 1.0/(n*bandwidth*(2.0*std::f64::consts::PI).sqrt());
+    points.iter().map(|&p| {
+        vals.iter().map(|&v| (-0.5*((p-v)/bandwidth).powi(2)).exp()).sum::<f64>()*scale
+    }).collect()
+}
+
+// Splits the values into bins. Returns the edges of the bins and how many values
+// fall in each of them. A bin contains its left edge, the last one both edges.
+// Values outside the edges are left out.
+pub fn histogram<T: Into<f64>+Copy>(vals: &[T], bins: &Bins) -> (Vec<f64>, Vec<usize>) {
+    let mut sorted: Vec<f64> = vals.iter().map(|&v| v.into()).filter(|v| v.is_finite()).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = sorted.len() as f64;
+    let (low, high) = match (sorted.first(), sorted.last()) {
+        (Some(&low), Some(&high)) if low < high => (low, high),
+        (Some(&value), _) => (value-0.5, value+0.5),
+        _ => (0.0, 1.0),
+    };
+    // The rules give a width, which is turned into a count covering the range
+    let from_width = |width: f64| {
+        if width > 0.0 { ((high-low)/width).ceil().max(1.0) as usize } else { 1 }
+    };
+    let count = match bins {
+        Bins::Count(count) => *count,
+        Bins::Edges(_) => 0,
+        Bins::Sturges => n.log2().ceil().max(0.0) as usize+1,
+        Bins::Scott => {
+            let mean = sorted.iter().sum::<f64>()/n;
+            let deviation = (sorted.iter().map(|v| (v-mean).powi(2)).sum::<f64>()/n).sqrt();
+            from_width(3.49*deviation/n.cbrt())
+        },
+        Bins::FreedmanDiaconis => {
+            if sorted.is_empty() {
+                1
+            } else {
+                from_width(2.0*(percentile(&sorted, 0.75)-percentile(&sorted, 0.25))/n.cbrt())
+            }
+        },
+    };
+    let edges = match bins {
+        // Given edges are put in order, edges that aren't numbers or repeat one
+        // before would make bins without a width
+        Bins::Edges(edges) => {
+            let mut edges: Vec<f64> = edges.iter().copied().filter(|e| e.is_finite()).collect();
+            edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            edges.dedup();
+            edges
+        },
+        _ => {
+            let mut edges = Linspace::linspace(low, high, count.max(1));
+            // Rounding must not leave the largest value outside
+            *edges.last_mut().unwrap() = high;
+            edges
+        },
+    };
+    let nbins = edges.len().saturating_sub(1);
+    let mut counts = vec![0; nbins];
+    if nbins == 0 {
+        return (edges, counts);
+    }
+    for &value in sorted.iter() {
+        if value < edges[0] || value > edges[nbins] {
+            continue;
+        }
+        let bin = edges.partition_point(|&edge| edge <= value).saturating_sub(1);
+        counts[bin.min(nbins-1)] += 1;
+    }
+    (edges, counts)
+}
+
 pub fn double_min<T: Into<(f64, f64)>+Copy>(vals: &[T]) -> (f64,f64) {
     let mut min_1 = std::f64::MAX;
     let mut min_2 = min_1;
@@ -109,12 +326,47 @@ impl Axes2D {
 }
 
 
+// Anything that can be drawn in a 2D plot
+pub enum Series {
+    Line(Line2D),
+    Bar(Bar2D),
+    Hist(Hist2D),
+    Fill(FillBetween),
+    Box(Box2D),
+    Violin(Violin2D),
+    Heatmap(Heatmap2D),
+    Contour(Contour2D),
+    Quiver(Quiver2D),
+    Stream(Stream2D),
+    Candlestick(Candlestick),
+}
+
+impl Series {
+    // A short name of the kind of series
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Series::Line(_) => "line",
+            Series::Bar(_) => "bar",
+            Series::Hist(_) => "hist",
+            Series::Fill(_) => "fill",
+            Series::Box(_) => "box",
+            Series::Violin(_) => "violin",
+            Series::Heatmap(_) => "heatmap",
+            Series::Contour(_) => "contour",
+            Series::Quiver(_) => "quiver",
+            Series::Stream(_) => "stream",
+            Series::Candlestick(_) => "candle",
+        }
+    }
+}
+
 pub struct Plot2D {
     title: String,
     xlabel: String,
     ylabel: String,
     axes: Grid,
-    lines: Vec<Line2D>,
+    // Drawn in the order they were added, so later series cover earlier ones
+    series: Vec<Series>,
     
 }
 #[derive(Debug)]
@@ -173,9 +425,17 @@ pub struct Plot3D {
     zlabel: String,
     axes: Grid3D,
     surface: Option<Surface3D>,
+    // Camera angles (elevation, azimuth) in degrees
+    view: (f32, f32),
     
 }
 
+// The azimuth of the frame i of a turn that starts at start, between -360 and 0
+// degrees where plot3d moves the axes to the side facing the camera
+fn turntable_azimuth(start: f32, i: usize, frames: usize) -> f32 {
+    (start+360.0*i as f32/frames as f32).rem_euclid(360.0)-360.0
+}
+
 pub struct Colormap(pub Vec<(f32,f32,f32)>);
 
 impl Colormap {
@@ -217,7 +477,33 @@ impl Colormap {
 
 
     }
-    
+
+    // Whether there is a colormap with the name
+    pub fn is_known(name: &str) -> bool {
+        matches!(name, "hot" | "gray")
+    }
+
+    // The color at t between 0 for the smallest and 1 for the largest value
+    pub fn color(name: &str, t: f32) -> (f32,f32,f32) {
+        let t = t.clamp(0.0, 1.0);
+        match name {
+            "hot" => ((3.0*t).min(1.0), (3.0*t-1.0).clamp(0.0, 1.0), (3.0*t-2.0).clamp(0.0, 1.0)),
+            "gray" => (t, t, t),
+            _ => panic!("Not a valid colormap: {}", name),
+        }
+    }
+
+    // The colors of the values spread evenly between the smallest and the largest
+    // finite one, so equal steps in value are equal steps in color. Values that
+    // aren't finite get none.
+    pub fn normalized(name: &str, data: &[f32]) -> Vec<Option<(f32,f32,f32)>> {
+        let finite = data.iter().copied().filter(|v| v.is_finite());
+        let min = finite.clone().fold(f32::INFINITY, f32::min);
+        let max = finite.fold(f32::NEG_INFINITY, f32::max);
+        let range = if max > min {max-min} else {1.0};
+        data.iter().map(|&v| if v.is_finite() {Some(Colormap::color(name, (v-min)/range))} else {None}).collect()
+    }
+
 }
 
 pub struct Surface3D {
@@ -238,11 +524,254 @@ pub struct Grid3D {
 pub struct Line2D {
     color: Color,
     pub data: Vec<(f64, f64)>,
-    pub linestyle: String,
+    style: LineStyle,
+    legend: Option<String>,
+    // Diameter of the markers in pixels
+    marker_size: f32,
+    edge_color: Option<Color>,
+    face_color: Option<Color>,
+    alpha: f32,
+    // Distances below and above every point, or left and right for x
+    xerr: Option<Vec<(f64, f64)>>,
+    yerr: Option<Vec<(f64, f64)>>,
+    error_style: ErrorStyle,
+    // Width of the caps in pixels
+    cap_size: f32,
+}
+
+
+
+#[derive(Debug)]
+pub struct Bar2D {
+    color: Color,
+    pub positions: Vec<f64>,
+    pub values: Vec<f64>,
+    // Names of the positions when the bars are categorical
+    labels: Option<Vec<String>>,
+    // Width in data units shared by all series at a position
+    width: f64,
+    horizontal: bool,
+    mode: BarMode,
     legend: Option<String>,
 }
 
+// How a bar series shares its positions with the series before it, either
+// next to them or on top of the last one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarMode {
+    Grouped,
+    Stacked,
+}
+
+// How histogram() picks the bins. The rules choose the number of bins from the
+// number of values and how spread out they are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bins {
+    Count(usize),
+    Edges(Vec<f64>),
+    Sturges,
+    Scott,
+    FreedmanDiaconis,
+}
+
+// Heights of the histogram bins. A density divides the counts by the number of
+// values and the width of the bins, so the bins add up to an area of one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    Count,
+    Density,
+    Cumulative,
+}
+
+// Whether the bins of a histogram are filled or only their outline is drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistType {
+    Filled,
+    Step,
+}
+
+#[derive(Debug)]
+pub struct Hist2D {
+    color: Color,
+    edges: Vec<f64>,
+    counts: Vec<usize>,
+    normalization: Normalization,
+    hist_type: HistType,
+    legend: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct FillBetween {
+    color: Color,
+    alpha: f32,
+    x: Vec<f64>,
+    // The lower and upper curves
+    y1: Vec<f64>,
+    y2: Vec<f64>,
+    // Only the points where the mask is true are filled
+    mask: Option<Vec<bool>>,
+    legend: Option<String>,
+}
+
+// Where the whiskers of a box end, 1.5 times the interquartile range from the
+// box or at the percentiles, from 0 to 100, of the group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Whiskers {
+    Iqr,
+    Percentile(f64, f64),
+}
+
+// Box plots of groups of values at the positions 0, 1, 2, ...
+#[derive(Debug)]
+pub struct Box2D {
+    color: Color,
+    groups: Vec<Vec<f64>>,
+    labels: Vec<String>,
+    width: f64,
+    whiskers: Whiskers,
+    notch: bool,
+    legend: Option<String>,
+}
+
+// Where the parts of a box are, in data coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxStats {
+    pub median: f64,
+    pub q1: f64,
+    pub q3: f64,
+    pub whisker_low: f64,
+    pub whisker_high: f64,
+    pub outliers: Vec<f64>,
+    // The 95% confidence interval of the median
+    pub notch: (f64, f64),
+}
+
+// Violin plots of groups of values at the positions 0, 1, 2, ...
+#[derive(Debug)]
+pub struct Violin2D {
+    color: Color,
+    groups: Vec<Vec<f64>>,
+    labels: Vec<String>,
+    width: f64,
+    bandwidth: Option<f64>,
+    // Number of points along each outline
+    points: usize,
+    legend: Option<String>,
+}
+
+// A matrix drawn as colored cells. Row i and column j is the cell from j to
+// j+1 along x and from i to i+1 along y, unless the extent says otherwise.
+pub struct Heatmap2D {
+    data: DMatrix<f32>,
+    colormap: String,
+    // [x0, x1, y0, y1] covered by the whole matrix
+    extent: Option<[f64;4]>,
+    edges: Option<Color>,
+    colorbar: bool,
+}
+
+// Contour lines or filled contours of z over the grid of x and y, like the
+// matrices from meshgrid
+pub struct Contour2D {
+    x: DMatrix<f32>,
+    y: DMatrix<f32>,
+    z: DMatrix<f32>,
+    levels: Vec<f64>,
+    // Fills the bands between the levels instead of drawing the lines
+    filled: bool,
+    color: Color,
+    colormap: String,
+    // Writes the level into each line
+    labels: bool,
+}
+
+// Arrows of the vectors (u, v) at the points of the grid of x and y
+pub struct Quiver2D {
+    x: DMatrix<f32>,
+    y: DMatrix<f32>,
+    u: DMatrix<f32>,
+    v: DMatrix<f32>,
+    color: Color,
+    // Data units per unit of the vectors, fitted to the grid if not set
+    scale: Option<f64>,
+}
+
+// Streamlines of the field (u, v) over the grid of x and y
+pub struct Stream2D {
+    x: DMatrix<f32>,
+    y: DMatrix<f32>,
+    u: DMatrix<f32>,
+    v: DMatrix<f32>,
+    color: Color,
+    density: f64,
+}
+
+// Open, high, low and close prices at times given as seconds since the Unix
+// epoch in UTC. A candle is up when it closes at or above its open.
+pub struct Candlestick {
+    timestamps: Vec<i64>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    // Drawn as bars under the plot when given
+    volume: Option<Vec<f64>>,
+    up_color: Color,
+    down_color: Color,
+    // Width of the bodies as a fraction of the room of one candle
+    width: f64,
+    // Puts the candles one after the other so that gaps in time, like
+    // weekends, take no room on the x axis
+    skip_gaps: bool,
+}
+
+// Colors the wedges of pies take in turn when none are given
+const PIE_COLORS: [Color; 8] = [
+    Color(0.12, 0.47, 0.71, 1.0),
+    Color(1.0, 0.5, 0.05, 1.0),
+    Color(0.17, 0.63, 0.17, 1.0),
+    Color(0.84, 0.15, 0.16, 1.0),
+    Color(0.58, 0.4, 0.74, 1.0),
+    Color(0.55, 0.34, 0.29, 1.0),
+    Color(0.89, 0.47, 0.76, 1.0),
+    Color(0.5, 0.5, 0.5, 1.0),
+];
+
+// A pie or donut chart of the shares of the values. It has no axes, the wedges
+// go around the middle of the window counterclockwise.
+pub struct Pie {
+    title: String,
+    values: Vec<f64>,
+    labels: Vec<String>,
+    colors: Vec<Color>,
+    // How far each wedge is moved out of the pie, as a fraction of the radius
+    explode: Vec<f64>,
+    // Radius of the hole as a fraction of the radius, 0 for a full pie
+    hole: f64,
+    // Degrees counterclockwise from 3 o'clock where the first wedge starts
+    start_angle: f64,
+    percentages: bool,
+}
+
+// The unit of the angle labels of a polar plot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngleUnit {
+    Degrees,
+    Radians,
+}
 
+// Lines in polar coordinates, given as (theta, r) with theta in radians
+// counterclockwise from 3 o'clock. The angle unit only changes the labels.
+pub struct PolarPlot {
+    title: String,
+    lines: Vec<Line2D>,
+    // The radius at the center and at the outer circle, from 0 to the largest
+    // radius of the lines unless it was set
+    rlim: Option<[f64;2]>,
+    angle_unit: AngleUnit,
+    // Number of circles of the grid
+    rings: usize,
+}
 
 pub struct Grid {
     pub axes: Axes2D,
@@ -263,6 +792,7 @@ impl Plot3D {
             zlabel: String::from("z"),
             axes: Grid3D::default(),
             surface: None,
+            view: DEFAULT_ANGLES,
         }
     }
 
@@ -354,6 +884,78 @@ impl Plot3D {
     pub fn get_title(&self) -> &str {
         &self.title
     }
+
+    // Lays out the plot in pixel space for the given size, seen from the camera
+    // angles (elevation, azimuth) in degrees.
+    pub fn scene(&self, width: f32, height: f32, angles: (f32, f32)) -> Scene {
+        scene::plot3d(self, width, height, angles)
+    }
+
+    // Sets the camera angles that the window starts with and that the headless
+    // renderers use.
+    pub fn view(mut self, azimuth: f32, elevation: f32) -> Self {
+        self.view = (elevation, azimuth);
+        self
+    }
+
+    pub fn get_view(&self) -> (f32, f32) {
+        self.view
+    }
+
+    // Rasterizes the plot on the CPU, seen from the plot's view. Returns the
+    // pixels row by row as RGBA.
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        raster::rgba(&raster::render(&self.scene(width as f32, height as f32, self.view)))
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        let pixmap = raster::render(&self.scene(width as f32, height as f32, self.view));
+        raster::save_png(&pixmap, path)
+    }
+
+    // The camera is placed the same way as in the window. Use DEFAULT_ANGLES for
+    // the view the window starts with.
+    pub fn to_svg_string(&self, width: u32, height: u32, azimuth: f32, elevation: f32) -> String {
+        svg::render(&self.scene(width as f32, height as f32, (elevation, azimuth)))
+    }
+
+    pub fn save_svg<P: AsRef<Path>>(&self, path: P, width: u32, height: u32, azimuth: f32,
+                                    elevation: f32) -> io::Result<()> {
+        svg::save(&self.scene(width as f32, height as f32, (elevation, azimuth)), path)
+    }
+
+    // Draws the plot in the terminal through the Kitty or Sixel graphics protocol.
+    // Terminals without graphics get the path of a PNG file instead.
+    pub fn show_inline(&self) -> io::Result<()> {
+        let (width, height) = INLINE_SIZE;
+        inline::show_or_save(&self.render_rgba(width, height), width, height, "plot3d")
+    }
+
+    // Writes an animated GIF where the camera turns a full revolution around the
+    // plot. The elevation of the plot's view is kept and the azimuth starts at its
+    // azimuth.
+    pub fn save_turntable_gif<P: AsRef<Path>>(&self, path: P, frames: usize, fps: f32) -> io::Result<()> {
+        if frames == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "A turntable needs at least one frame"));
+        }
+        if fps.is_nan() || fps <= 0.0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "The frame rate has to be positive"));
+        }
+        let (width, height) = TURNTABLE_SIZE;
+        let (elevation, azimuth) = self.view;
+        let images: Vec<Vec<u8>> = (0..frames).map(|i| {
+            let angles = (elevation, turntable_azimuth(azimuth, i, frames));
+            raster::rgba(&raster::render(&self.scene(width as f32, height as f32, angles)))
+        }).collect();
+        raster::save_gif(&images, width, height, fps, path)
+    }
+
+    // Writes the plot, seen from the plot's view, as a single page vector PDF.
+    pub fn save_pdf<P: AsRef<Path>>(&self, path: P, page: PageSize) -> io::Result<()> {
+        let (width, height) = page.pixels();
+        pdf::save(&self.scene(width, height, self.view), path)
+    }
+
     pub fn show(self) {
 
         Window3D::run(Settings{
@@ -385,11 +987,9 @@ impl Plot2D {
 
     pub fn plot(l: Line2D) -> Self {
         let mut default = Self::new();
-        let (x_min, y_min): (f64,f64) = double_min(&l.data);
-        let (x_max, y_max): (f64,f64) = double_max(&l.data);
-        let g = Grid::new(Axes2D::new().axes(&[x_min, x_max], &[y_min, y_max]), "none");
-        default.axes = g;
-        default.lines.push(l);
+        default.series.push(Series::Line(l));
+        // Makes room for the error bars too
+        default.autoscale();
         default
 
     }
@@ -403,7 +1003,7 @@ impl Plot2D {
         let g = Grid::new(Axes2D::new().axes(&[x_min, x_max], &[y_min, y_max]), "none");
         let line = Line2D::new(x, y);
         default.axes = g;
-        default.lines.push(line);
+        default.series.push(Series::Line(line));
         default
 
     }
@@ -414,116 +1014,1979 @@ impl Plot2D {
             xlabel:  String::from("x"),
             ylabel: String::from("y"),
             axes: Grid::default(),
-            lines: Vec::new(),
+            series: Vec::new(),
 
         }
     }
 
-    pub fn show(self) {
-        Window::run(Settings{
-        window: window::Settings::default(),
-        flags: self,
-        default_font: None,
-        default_text_size: 20,
-        antialiasing: true,
-        }).unwrap();
-        
+    pub fn bar(b: Bar2D) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Bar(b));
+        default.autoscale();
+        default
     }
 
-    pub fn grid(mut self, grid: &str) -> Self {
-        self.axes.grid = String::from(grid);
-        self
+    pub fn hist<T: Into<f64> + Copy>(data: &[T], bins: Bins) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Hist(Hist2D::new(data, bins)));
+        default.autoscale();
+        default
     }
 
-//    pub fn get_axes(xlim: &[f64;2], ylim: &[f64;2], grid: &str) {
-//        
-//    }
-    pub fn get_axes(&self) -> &Grid {
-        &self.axes
+    pub fn fill_between(f: FillBetween) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Fill(f));
+        default.autoscale();
+        default
     }
 
-    pub fn get_lines(&self) -> &Vec<Line2D> {
-        &self.lines
+    // Stacks the areas under every y on top of the ones before, see
+    // FillBetween::stacked.
+    pub fn stacked_area<T: Into<f64> + Copy>(x: &[T], ys: &[&[T]], colors: &[Color]) -> Self {
+        let mut default = Self::new();
+        default.series.extend(FillBetween::stacked(x, ys, colors).into_iter().map(Series::Fill));
+        default.autoscale();
+        default
     }
 
-    pub fn add_line(&mut self, line: Line2D) {
-        self.lines.push(line);
+    pub fn heatmap(h: Heatmap2D) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Heatmap(h));
+        default.autoscale();
+        default
     }
 
+    pub fn contour(x: DMatrix<f32>, y: DMatrix<f32>, z: DMatrix<f32>, levels: &[f64]) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Contour(Contour2D::new(x, y, z, levels)));
+        default.autoscale();
+        default
+    }
 
-}
+    pub fn contourf(x: DMatrix<f32>, y: DMatrix<f32>, z: DMatrix<f32>, levels: &[f64]) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Contour(Contour2D::new(x, y, z, levels).filled()));
+        default.autoscale();
+        default
+    }
 
-impl Grid {
-    pub fn default() -> Self {
-        Self {
-            axes: Axes2D{xlim: [0.0, 1.0], ylim: [0.0, 1.0], nvalues: 10},
-            grid: String::from("none"),
-        }
+    pub fn quiver(x: DMatrix<f32>, y: DMatrix<f32>, u: DMatrix<f32>, v: DMatrix<f32>) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Quiver(Quiver2D::new(x, y, u, v)));
+        default.autoscale();
+        default
     }
-    pub fn get_axes(&self) -> &Axes2D {
-        &self.axes
+
+    pub fn streamplot(x: DMatrix<f32>, y: DMatrix<f32>, u: DMatrix<f32>, v: DMatrix<f32>) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Stream(Stream2D::new(x, y, u, v)));
+        default.autoscale();
+        default
     }
-    pub fn new(axes: Axes2D, grid: &str) -> Self {
-        Self {
-            axes,
-            grid: grid.to_owned(),
-        }
+
+    pub fn candlestick(c: Candlestick) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Candlestick(c));
+        default.autoscale();
+        default
     }
-}
 
-impl Grid3D {
-    pub fn default() -> Self {
-        Self {
-            axes: Axes3D{xlim: [0.0, 1.0], ylim: [0.0, 1.0], zlim: [0.0, 1.0], x_spacing: 1.0,
-            y_spacing: 1.0, z_spacing: 1.0},
-            grid: String::from("both"),
-        }
+    pub fn boxplot<T: Into<f64> + Copy>(groups: &[&[T]]) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Box(Box2D::new(groups)));
+        default.autoscale();
+        default
     }
-    pub fn get_axes(&self) -> &Axes3D {
-        &self.axes
+
+    pub fn violinplot<T: Into<f64> + Copy>(groups: &[&[T]]) -> Self {
+        let mut default = Self::new();
+        default.series.push(Series::Violin(Violin2D::new(groups)));
+        default.autoscale();
+        default
     }
-    pub fn new(axes: Axes3D, grid: &str) -> Self {
-        Self {
-            axes,
-            grid: grid.to_owned(),
+
+    // Fits the axes around every series. Bars and histograms start at zero, so
+    // zero is always included when there are any.
+    fn autoscale(&mut self) {
+        let mut xlim = [f64::MAX, f64::MIN];
+        let mut ylim = xlim;
+        let mut include = |x: f64, y: f64| {
+            xlim = [xlim[0].min(x), xlim[1].max(x)];
+            ylim = [ylim[0].min(y), ylim[1].max(y)];
+        };
+        for line in self.get_lines() {
+            if let Some((baseline, _)) = line.get_stems() {
+                for &(x, _) in line.get_data().iter().filter(|(x, _)| x.is_finite()) {
+                    include(x, baseline);
+                }
+            }
+            for [x0, x1, y0, y1] in line.get_error_ranges() {
+                if x0.is_finite() && x1.is_finite() && y0.is_finite() && y1.is_finite() {
+                    include(x0, y0);
+                    include(x1, y1);
+                }
+            }
+        }
+        for fill in self.get_fills() {
+            for polygon in fill.get_polygons() {
+                for &(x, y) in polygon.iter().filter(|(x, y)| x.is_finite() && y.is_finite()) {
+                    include(x, y);
+                }
+            }
+        }
+        let grids = self.get_contours().into_iter().map(|c| (&c.x, &c.y))
+            .chain(self.get_quivers().into_iter().map(|q| (&q.x, &q.y)))
+            .chain(self.get_streams().into_iter().map(|s| (&s.x, &s.y)));
+        for (xs, ys) in grids {
+            for (&x, &y) in xs.iter().zip(ys.iter()) {
+                include(x as f64, y as f64);
+            }
+        }
+        for quiver in self.get_quivers() {
+            for [_, (x, y)] in quiver.get_arrows() {
+                include(x, y);
+            }
+        }
+        for heatmap in self.get_heatmaps() {
+            let [x0, x1, y0, y1] = heatmap.get_extent();
+            include(x0, y0);
+            include(x1, y1);
+        }
+        // Boxes and violins get the whole slot of their category
+        for boxes in self.get_boxes() {
+            for (i, stats) in boxes.get_stats().iter().enumerate().filter_map(|(i, s)| Some((i, s.as_ref()?))) {
+                include(i as f64-0.5, stats.whisker_low);
+                include(i as f64+0.5, stats.whisker_high);
+                for &outlier in stats.outliers.iter() {
+                    include(i as f64, outlier);
+                }
+            }
+        }
+        for candles in self.get_candlesticks() {
+            for (i, x) in candles.get_positions().into_iter().enumerate() {
+                let [_, high, low, _] = candles.get_prices(i);
+                include(x-0.5, low);
+                include(x+0.5, high);
+            }
+        }
+        for violin in self.get_violins() {
+            let last = violin.groups.len() as f64-0.5;
+            for &(_, y) in violin.get_polygons().iter().flatten() {
+                include(-0.5, y);
+                include(last, y);
+            }
+        }
+        let rects = self.get_bar_rects().into_iter().chain(self.get_hist_rects())
+            .filter(|(_, rect)| rect.iter().all(|v| v.is_finite()));
+        for (_, [x0, x1, y0, y1]) in rects {
+            include(x0, y0);
+            include(x1, y1);
+        }
+        // The value axis of bars gets room beyond their ends, but not beyond the
+        // baseline they start from
+        let pad = |lim: &mut [f64;2]| {
+            let margin = 0.05*(lim[1]-lim[0]);
+            if lim[0] < 0.0 {
+                lim[0] -= margin;
+            }
+            if lim[1] > 0.0 {
+                lim[1] += margin;
+            }
+        };
+        if self.get_bars().into_iter().any(|bar| !bar.horizontal) {
+            pad(&mut ylim);
+        }
+        if self.get_bars().into_iter().any(|bar| bar.horizontal) {
+            pad(&mut xlim);
+        }
+        if xlim[0] <= xlim[1] {
+            self.axes.axes = Axes2D::new().axes(&xlim, &ylim);
         }
     }
-}
 
-impl Line2D {
+    pub fn show(self) {
+        Window::open(Shown::Plot(self));
+    }
 
-    pub fn new<T: Into<f64> + Copy>(x: &[T], y: &[T]) -> Self {
-        let data: Vec<(f64,f64)> = x.iter().map(|&x| x.into()).zip(y.iter().map(|&y| y.into())).collect();
-        Self {
-            color: Color::BLACK,
-            data,
-            linestyle: "-".to_owned(),
-            legend: None,
-            
-        }
+    // Lays out the plot in pixel space for the given size.
+    pub fn scene(&self, width: f32, height: f32) -> Scene {
+        scene::plot2d(self, width, height)
     }
-    
-    pub fn color(mut self, color: Color) -> Self {
-       self.color = color;
-       self
+
+    // Rasterizes the plot on the CPU. Returns the pixels row by row as RGBA.
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        raster::rgba(&raster::render(&self.scene(width as f32, height as f32)))
     }
 
-    pub fn get_color(&self) -> &Color {
-        &self.color
-        
+    // Rasterizes the plot on the CPU and writes it to a PNG file.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        let pixmap = raster::render(&self.scene(width as f32, height as f32));
+        raster::save_png(&pixmap, path)
     }
 
-    pub fn get_data(&self) -> &Vec<(f64,f64)> {
-       &self.data 
+    pub fn to_svg_string(&self, width: u32, height: u32) -> String {
+        svg::render(&self.scene(width as f32, height as f32))
     }
 
-    pub fn linestyle(mut self, linestyle: &str) -> Self {
-        self.linestyle = linestyle.to_owned();
-        self
+    pub fn save_svg<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        svg::save(&self.scene(width as f32, height as f32), path)
     }
 
+    pub fn to_html_string(&self, width: u32, height: u32) -> String {
+        html::render(self, width, height)
+    }
+
+    // Writes an interactive page with tooltips, zooming and a legend. It doesn't
+    // load anything from the network so it can be sent as a single file.
+    pub fn save_html<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        html::save(self, path, width, height)
+    }
+
+    pub fn to_tikz_string(&self) -> String {
+        tikz::render(self)
+    }
+
+    // Writes the plot as a pgfplots axis environment to \input in a LaTeX document.
+    pub fn save_tikz<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        tikz::save(self, path)
+    }
+
+    // Writes the plot as a single page vector PDF, e.g. PageSize::inches(6.0, 4.0).
+    pub fn save_pdf<P: AsRef<Path>>(&self, path: P, page: PageSize) -> io::Result<()> {
+        let (width, height) = page.pixels();
+        pdf::save(&self.scene(width, height), path)
+    }
+
+    // Draws the plot in the terminal through the Kitty or Sixel graphics protocol.
+    // Terminals without graphics get the path of a PNG file instead, print draws
+    // the plot as text in any terminal.
+    pub fn show_inline(&self) -> io::Result<()> {
+        let (width, height) = INLINE_SIZE;
+        inline::show_or_save(&self.render_rgba(width, height), width, height, "plot")
+    }
+
+    // Draws the plot as text with braille characters and ANSI colors.
+    pub fn to_terminal(&self, cols: usize, rows: usize) -> String {
+        terminal::render(self, cols, rows)
+    }
+
+    // Prints the plot to stdout, e.g. over SSH where show can't open a window.
+    // The size is taken from COLUMNS and LINES when the shell exports them.
+    pub fn print(&self) {
+        let size = |name: &str, default: usize| {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        };
+        print!("{}", self.to_terminal(size("COLUMNS", 80), size("LINES", 24).saturating_sub(1)));
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+    pub fn xlabel(mut self, xlabel: &str) -> Self {
+        self.xlabel = xlabel.to_owned();
+        self
+    }
+    pub fn ylabel(mut self, ylabel: &str) -> Self {
+        self.ylabel = ylabel.to_owned();
+        self
+    }
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+    pub fn get_xlabel(&self) -> &str {
+        &self.xlabel
+    }
+    pub fn get_ylabel(&self) -> &str {
+        &self.ylabel
+    }
+
+    pub fn grid(mut self, grid: &str) -> Self {
+        self.axes.grid = String::from(grid);
+        self
+    }
+
+//    pub fn get_axes(xlim: &[f64;2], ylim: &[f64;2], grid: &str) {
+//        
+//    }
+    pub fn get_axes(&self) -> &Grid {
+        &self.axes
+    }
+
+    pub fn get_series(&self) -> &Vec<Series> {
+        &self.series
+    }
+
+    pub fn get_lines(&self) -> Vec<&Line2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Line(line) => Some(line),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_line(&mut self, line: Line2D) {
+        self.series.push(Series::Line(line));
+    }
+
+    pub fn get_bars(&self) -> Vec<&Bar2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Bar(bar) => Some(bar),
+            _ => None,
+        }).collect()
+    }
+
+    // Adds another bar series and rescales the axes to fit it.
+    pub fn add_bar(&mut self, bar: Bar2D) {
+        self.series.push(Series::Bar(bar));
+        self.autoscale();
+    }
+
+    // The rectangle [x0, x1, y0, y1] in data coordinates of every bar, together
+    // with the index of its series. Grouped series are put side by side within
+    // the width of the bars, stacked ones on top of the series before them.
+    pub fn get_bar_rects(&self) -> Vec<(usize, [f64;4])> {
+        let mut rects = Vec::new();
+        // Every grouped series starts a slot that the stacked ones after it share
+        let slots: Vec<usize> = self.get_bars().into_iter().enumerate()
+            .scan(0, |slot, (k, bar)| {
+                if k > 0 && bar.mode == BarMode::Grouped {
+                    *slot += 1;
+                }
+                Some(*slot)
+            })
+            .collect();
+        let n = slots.last().map_or(1, |last| last+1) as f64;
+        // The tops of the positive and negative stacks of a slot at each position
+        let mut stacks: Vec<(usize, f64, f64, f64)> = Vec::new();
+        for (k, bar) in self.get_bars().into_iter().enumerate() {
+            let slot = slots[k];
+            for (&position, &value) in bar.positions.iter().zip(bar.values.iter()) {
+                let index = match stacks.iter().position(|s| s.0 == slot && s.1 == position) {
+                    Some(index) => index,
+                    None => {
+                        stacks.push((slot, position, 0.0, 0.0));
+                        stacks.len()-1
+                    },
+                };
+                let stack = &mut stacks[index];
+                let base = if value >= 0.0 {stack.2} else {stack.3};
+                if value >= 0.0 {
+                    stack.2 += value;
+                } else {
+                    stack.3 += value;
+                }
+                let start = position-bar.width*0.5+slot as f64*bar.width/n;
+                let end = position+bar.width*0.5-(n-1.0-slot as f64)*bar.width/n;
+                let rect = if bar.horizontal {
+                    [base, base+value, start, end]
+                } else {
+                    [start, end, base, base+value]
+                };
+                rects.push((k, rect));
+            }
+        }
+        rects
+    }
+
+    pub fn get_fills(&self) -> Vec<&FillBetween> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Fill(fill) => Some(fill),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_fill(&mut self, fill: FillBetween) {
+        self.series.push(Series::Fill(fill));
+        self.autoscale();
+    }
+
+    pub fn get_contours(&self) -> Vec<&Contour2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Contour(contour) => Some(contour),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_contour(&mut self, contour: Contour2D) {
+        self.series.push(Series::Contour(contour));
+        self.autoscale();
+    }
+
+    pub fn get_quivers(&self) -> Vec<&Quiver2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Quiver(quiver) => Some(quiver),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_quiver(&mut self, quiver: Quiver2D) {
+        self.series.push(Series::Quiver(quiver));
+        self.autoscale();
+    }
+
+    pub fn get_streams(&self) -> Vec<&Stream2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Stream(stream) => Some(stream),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_stream(&mut self, stream: Stream2D) {
+        self.series.push(Series::Stream(stream));
+        self.autoscale();
+    }
+
+    pub fn get_heatmaps(&self) -> Vec<&Heatmap2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Heatmap(heatmap) => Some(heatmap),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_heatmap(&mut self, heatmap: Heatmap2D) {
+        self.series.push(Series::Heatmap(heatmap));
+        self.autoscale();
+    }
+
+    pub fn get_boxes(&self) -> Vec<&Box2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Box(boxes) => Some(boxes),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_box(&mut self, boxes: Box2D) {
+        self.series.push(Series::Box(boxes));
+        self.autoscale();
+    }
+
+    pub fn get_violins(&self) -> Vec<&Violin2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Violin(violin) => Some(violin),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_violin(&mut self, violin: Violin2D) {
+        self.series.push(Series::Violin(violin));
+        self.autoscale();
+    }
+
+    pub fn get_candlesticks(&self) -> Vec<&Candlestick> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Candlestick(candle) => Some(candle),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_candlestick(&mut self, candles: Candlestick) {
+        self.series.push(Series::Candlestick(candles));
+        self.autoscale();
+    }
+
+    // The time at the x tick when the first candlestick series gives the x axis
+    // times, see Candlestick::get_tick_label
+    pub fn get_time_label(&self, x: f64, spacing: f64) -> Option<String> {
+        self.get_candlesticks().into_iter().next().map(|c| c.get_tick_label(x, spacing))
+    }
+
+    // The names of the positions of a categorical axis, and whether it is the y
+    // axis. The first series with names decides.
+    pub fn get_categories(&self) -> Option<(&Vec<String>, bool)> {
+        self.get_bars().into_iter().find_map(|bar| bar.get_labels().map(|labels| (labels, bar.is_horizontal())))
+            .or_else(|| self.get_boxes().into_iter().next().map(|b| (&b.labels, false)))
+            .or_else(|| self.get_violins().into_iter().next().map(|v| (&v.labels, false)))
+    }
+
+    pub fn get_hists(&self) -> Vec<&Hist2D> {
+        self.series.iter().filter_map(|s| match s {
+            Series::Hist(hist) => Some(hist),
+            _ => None,
+        }).collect()
+    }
+
+    pub fn add_hist(&mut self, hist: Hist2D) {
+        self.series.push(Series::Hist(hist));
+        self.autoscale();
+    }
+
+    // The rectangle [x0, x1, y0, y1] of every histogram bin with the index of its
+    // histogram, with the heights normalized.
+    pub fn get_hist_rects(&self) -> Vec<(usize, [f64;4])> {
+        let mut rects = Vec::new();
+        for (k, hist) in self.get_hists().into_iter().enumerate() {
+            let values = hist.get_values();
+            for (i, value) in values.into_iter().enumerate() {
+                rects.push((k, [hist.edges[i], hist.edges[i+1], 0.0, value]));
+            }
+        }
+        rects
+    }
+
+    // The outline of every histogram for the step type, from zero at the first
+    // edge over the tops of the bins back to zero at the last edge.
+    pub fn get_hist_outlines(&self) -> Vec<Vec<(f64, f64)>> {
+        let rects = self.get_hist_rects();
+        (0..self.get_hists().len()).map(|k| {
+            let mut outline = Vec::new();
+            for (_, [x0, x1, _, y]) in rects.iter().filter(|(n, _)| *n == k) {
+                if outline.is_empty() {
+                    outline.push((*x0, 0.0));
+                }
+                outline.push((*x0, *y));
+                outline.push((*x1, *y));
+            }
+            if let Some(&(x, _)) = outline.last() {
+                outline.push((x, 0.0));
+            }
+            outline
+        }).collect()
+    }
+
+
+}
+
+impl Grid {
+    pub fn default() -> Self {
+        Self {
+            axes: Axes2D{xlim: [0.0, 1.0], ylim: [0.0, 1.0], nvalues: 10},
+            grid: String::from("none"),
+        }
+    }
+    pub fn get_axes(&self) -> &Axes2D {
+        &self.axes
+    }
+    pub fn new(axes: Axes2D, grid: &str) -> Self {
+        Self {
+            axes,
+            grid: grid.to_owned(),
+        }
+    }
+}
+
+impl Grid3D {
+    pub fn default() -> Self {
+        Self {
+            axes: Axes3D{xlim: [0.0, 1.0], ylim: [0.0, 1.0], zlim: [0.0, 1.0], x_spacing: 1.0,
+            y_spacing: 1.0, z_spacing: 1.0},
+            grid: String::from("both"),
+        }
+    }
+    pub fn get_axes(&self) -> &Axes3D {
+        &self.axes
+    }
+    pub fn new(axes: Axes3D, grid: &str) -> Self {
+        Self {
+            axes,
+            grid: grid.to_owned(),
+        }
+    }
+}
+
+impl Line2D {
+
+    pub fn new<T: Into<f64> + Copy>(x: &[T], y: &[T]) -> Self {
+        let data: Vec<(f64,f64)> = x.iter().map(|&x| x.into()).zip(y.iter().map(|&y| y.into())).collect();
+        Self {
+            color: Color::BLACK,
+            data,
+            style: LineStyle{marker: None, draw: DrawStyle::Line},
+            legend: None,
+            marker_size: 6.0,
+            edge_color: None,
+            face_color: None,
+            alpha: 1.0,
+            xerr: None,
+            yerr: None,
+            error_style: ErrorStyle::Bars,
+            cap_size: 6.0,
+        }
+    }
+    
+    pub fn color(mut self, color: Color) -> Self {
+       self.color = color;
+       self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+        
+    }
+
+    pub fn get_data(&self) -> &Vec<(f64,f64)> {
+       &self.data 
+    }
+
+    // Panics on linestyles that aren't valid, LineStyle::parse returns an error
+    // for them instead
+    #[deprecated(note = "use style, marker and draw, or LineStyle::parse for strings")]
+    pub fn linestyle(mut self, linestyle: &str) -> Self {
+        self.style = LineStyle::parse(linestyle).unwrap_or_else(|e| panic!("{}", e));
+        self
+    }
+
+    pub fn style(mut self, style: LineStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn get_style(&self) -> LineStyle {
+        self.style
+    }
+
+    pub fn marker(mut self, marker: Marker) -> Self {
+        self.style.marker = Some(marker);
+        self
+    }
+
+    // How the points are joined, DrawStyle::None leaves only the markers
+    pub fn draw(mut self, draw: DrawStyle) -> Self {
+        self.style.draw = draw;
+        self
+    }
+
+    // Joins the points with horizontal and vertical lines
+    pub fn steps(mut self, step: Step) -> Self {
+        self.style.draw = DrawStyle::Steps(step);
+        self
+    }
+
+    // Draws the points as stems from the baseline, with circles on top unless
+    // there already is a marker
+    pub fn stem(mut self, baseline: f64) -> Self {
+        self.style.draw = DrawStyle::Stem(baseline);
+        self.style.marker = self.style.marker.or(Some(Marker::Circle));
+        self
+    }
+
+    // The corners of the line that joins the points, empty when they aren't joined
+    pub fn get_line_points(&self) -> Vec<(f64, f64)> {
+        let data = &self.data;
+        let step = match self.style.draw {
+            DrawStyle::Line => return data.clone(),
+            DrawStyle::Steps(step) => step,
+            DrawStyle::None | DrawStyle::Stem(_) => return Vec::new(),
+        };
+        let mut points = data.iter().take(1).cloned().collect::<Vec<_>>();
+        for pair in data.windows(2) {
+            let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+            match step {
+                Step::Pre => points.push((x0, y1)),
+                Step::Post => points.push((x1, y0)),
+                Step::Mid => {
+                    let middle = (x0+x1)*0.5;
+                    points.push((middle, y0));
+                    points.push((middle, y1));
+                },
+            }
+            points.push((x1, y1));
+        }
+        points
+    }
+
+    // The baseline and the vertical lines of a stem plot
+    pub fn get_stems(&self) -> Option<(f64, Vec<Segment2D>)> {
+        match self.style.draw {
+            DrawStyle::Stem(baseline) => {
+                Some((baseline, self.data.iter().map(|&(x, y)| [(x, baseline), (x, y)]).collect()))
+            },
+            _ => None,
+        }
+    }
+
+    pub fn marker_size(mut self, marker_size: f32) -> Self {
+        self.marker_size = marker_size;
+        self
+    }
+
+    pub fn get_marker_size(&self) -> f32 {
+        self.marker_size
+    }
+
+    pub fn edge_color(mut self, edge_color: Color) -> Self {
+        self.edge_color = Some(edge_color);
+        self
+    }
+
+    pub fn face_color(mut self, face_color: Color) -> Self {
+        self.face_color = Some(face_color);
+        self
+    }
+
+    // Opacity of the whole series, on top of the alpha of its colors
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    // The colors to draw with, after defaulting to the line color and applying alpha
+    pub fn get_line_color(&self) -> Color {
+        self.color.scale_alpha(self.alpha)
+    }
+
+    pub fn get_edge_color(&self) -> Color {
+        self.edge_color.unwrap_or(self.color).scale_alpha(self.alpha)
+    }
+
+    pub fn get_face_color(&self) -> Color {
+        self.face_color.unwrap_or(self.color).scale_alpha(self.alpha)
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
+
+    // The same error on both sides of every point
+    pub fn xerr<T: Into<f64> + Copy>(mut self, err: &[T]) -> Self {
+        self.xerr = Some(self.errors(err, err));
+        self
+    }
+
+    pub fn yerr<T: Into<f64> + Copy>(mut self, err: &[T]) -> Self {
+        self.yerr = Some(self.errors(err, err));
+        self
+    }
+
+    // Separate errors to the left and to the right of every point
+    pub fn xerr_asymmetric<T: Into<f64> + Copy>(mut self, lower: &[T], upper: &[T]) -> Self {
+        self.xerr = Some(self.errors(lower, upper));
+        self
+    }
+
+    // Separate errors below and above every point
+    pub fn yerr_asymmetric<T: Into<f64> + Copy>(mut self, lower: &[T], upper: &[T]) -> Self {
+        self.yerr = Some(self.errors(lower, upper));
+        self
+    }
+
+    fn errors<T: Into<f64> + Copy>(&self, lower: &[T], upper: &[T]) -> Vec<(f64, f64)> {
+        if lower.len() != self.data.len() || upper.len() != self.data.len() {
+            panic!("The line needs as many errors as points");
+        }
+        lower.iter().zip(upper.iter()).map(|(&l, &u)| (l.into(), u.into())).collect()
+    }
+
+    pub fn get_xerr(&self) -> Option<&Vec<(f64, f64)>> {
+        self.xerr.as_ref()
+    }
+
+    pub fn get_yerr(&self) -> Option<&Vec<(f64, f64)>> {
+        self.yerr.as_ref()
+    }
+
+    pub fn error_style(mut self, error_style: ErrorStyle) -> Self {
+        self.error_style = error_style;
+        self
+    }
+
+    pub fn get_error_style(&self) -> ErrorStyle {
+        self.error_style
+    }
+
+    pub fn cap_size(mut self, cap_size: f32) -> Self {
+        self.cap_size = cap_size;
+        self
+    }
+
+    pub fn get_cap_size(&self) -> f32 {
+        self.cap_size
+    }
+
+    // The x and y ranges [x0, x1, y0, y1] covered by the errors of every point
+    pub fn get_error_ranges(&self) -> Vec<[f64;4]> {
+        self.data.iter().enumerate().map(|(i, &(x, y))| {
+            let (left, right) = self.xerr.as_ref().and_then(|e| e.get(i).copied()).unwrap_or((0.0, 0.0));
+            let (below, above) = self.yerr.as_ref().and_then(|e| e.get(i).copied()).unwrap_or((0.0, 0.0));
+            [x-left, x+right, y-below, y+above]
+        }).collect()
+    }
+
+    // The outline of the band, along the upper ends of the y errors and back
+    // along the lower ones. None unless the y errors are drawn as a band.
+    pub fn get_band(&self) -> Option<Vec<(f64, f64)>> {
+        if self.error_style == ErrorStyle::Bars {
+            return None;
+        }
+        self.yerr.as_ref()?;
+        let ranges = self.get_error_ranges();
+        let mut band: Vec<(f64, f64)> = self.data.iter().zip(ranges.iter()).map(|(&(x, _), r)| (x, r[3])).collect();
+        band.extend(self.data.iter().zip(ranges.iter()).rev().map(|(&(x, _), r)| (x, r[2])));
+        Some(band)
+    }
+
+}
+impl Bar2D {
+
+    pub fn new<T: Into<f64> + Copy>(positions: &[T], values: &[T]) -> Self {
+        Self {
+            color: Color::BLUE,
+            positions: positions.iter().map(|&p| p.into()).collect(),
+            values: values.iter().map(|&v| v.into()).collect(),
+            labels: None,
+            width: 0.8,
+            horizontal: false,
+            mode: BarMode::Grouped,
+            legend: None,
+        }
+    }
+
+    // Bars at the positions 0, 1, 2, ... named by the labels
+    pub fn categories<T: Into<f64> + Copy>(labels: &[&str], values: &[T]) -> Self {
+        let positions: Vec<f64> = (0..labels.len()).map(|i| i as f64).collect();
+        let mut bar = Self::new(&positions, &values.iter().map(|&v| v.into()).collect::<Vec<f64>>());
+        bar.labels = Some(labels.iter().map(|l| l.to_string()).collect());
+        bar
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+
+    // Puts the positions on the y axis and lets the bars grow along x.
+    pub fn horizontal(mut self) -> Self {
+        self.horizontal = true;
+        self
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.horizontal
+    }
+
+    pub fn mode(mut self, mode: BarMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn get_mode(&self) -> BarMode {
+        self.mode
+    }
+
+    pub fn get_labels(&self) -> Option<&Vec<String>> {
+        self.labels.as_ref()
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
+
+}
+
+impl FillBetween {
+
+    pub fn new<T: Into<f64> + Copy>(x: &[T], y1: &[T], y2: &[T]) -> Self {
+        Self {
+            color: Color::BLUE,
+            alpha: 1.0,
+            x: x.iter().map(|&x| x.into()).collect(),
+            y1: y1.iter().map(|&y| y.into()).collect(),
+            y2: y2.iter().map(|&y| y.into()).collect(),
+            mask: None,
+            legend: None,
+        }
+    }
+
+    // The area between the curve and the horizontal line at baseline
+    pub fn baseline<T: Into<f64> + Copy>(x: &[T], y: &[T], baseline: f64) -> Self {
+        let x: Vec<f64> = x.iter().map(|&x| x.into()).collect();
+        let y: Vec<f64> = y.iter().map(|&y| y.into()).collect();
+        Self::new(&x, &vec![baseline; y.len()], &y)
+    }
+
+    // One area for every y, filled from the sum of the ones before up to the sum
+    // including it. The colors are repeated when there are fewer than areas.
+    pub fn stacked<T: Into<f64> + Copy>(x: &[T], ys: &[&[T]], colors: &[Color]) -> Vec<Self> {
+        let x: Vec<f64> = x.iter().map(|&x| x.into()).collect();
+        let mut base = vec![0.0; x.len()];
+        let mut fills = Vec::new();
+        for (i, y) in ys.iter().enumerate() {
+            let top: Vec<f64> = base.iter().zip(y.iter()).map(|(&b, &y)| b+y.into()).collect();
+            let mut fill = Self::new(&x, &base, &top);
+            if !colors.is_empty() {
+                fill = fill.color(colors[i%colors.len()]);
+            }
+            fills.push(fill);
+            base = top;
+        }
+        fills
+    }
+
+    // Fills only where the mask is true, like the where argument of matplotlib
+    pub fn mask(mut self, mask: &[bool]) -> Self {
+        self.mask = Some(mask.to_vec());
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn get_fill_color(&self) -> Color {
+        self.color.scale_alpha(self.alpha)
+    }
+
+    // The filled polygons, one for every run of points that aren't masked out.
+    // Each goes along the first curve and back along the second one.
+    pub fn get_polygons(&self) -> Vec<Vec<(f64, f64)>> {
+        let n = self.x.len().min(self.y1.len()).min(self.y2.len());
+        let mut polygons = Vec::new();
+        let mut start = 0;
+        for i in 0..=n {
+            let masked = i == n || self.mask.as_ref().is_some_and(|m| !m.get(i).copied().unwrap_or(false));
+            if !masked {
+                continue;
+            }
+            if i > start+1 {
+                let mut polygon: Vec<(f64, f64)> = (start..i).map(|j| (self.x[j], self.y1[j])).collect();
+                polygon.extend((start..i).rev().map(|j| (self.x[j], self.y2[j])));
+                polygons.push(polygon);
+            }
+            start = i+1;
+        }
+        polygons
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
+
+}
+
+impl Contour2D {
+
+    pub fn new(x: DMatrix<f32>, y: DMatrix<f32>, z: DMatrix<f32>, levels: &[f64]) -> Self {
+        // Levels that aren't finite have no lines
+        let mut levels: Vec<f64> = levels.iter().copied().filter(|l| l.is_finite()).collect();
+        levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Self {
+            x,
+            y,
+            z,
+            levels,
+            filled: false,
+            color: Color::BLACK,
+            colormap: String::from("hot"),
+            labels: true,
+        }
+    }
+
+    // Fills the bands between the levels with the colors of the colormap
+    pub fn filled(mut self) -> Self {
+        self.filled = true;
+        self.labels = false;
+        self
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    // One of the names Colormap knows, "hot" or "gray"
+    pub fn colormap(mut self, colormap: &str) -> Self {
+        assert!(Colormap::is_known(colormap), "Not a valid colormap: {}", colormap);
+        self.colormap = String::from(colormap);
+        self
+    }
+
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn has_labels(&self) -> bool {
+        self.labels
+    }
+
+    pub fn get_levels(&self) -> &Vec<f64> {
+        &self.levels
+    }
+
+    // The level as it is written on its lines, without the rounding errors of
+    // levels that were computed
+    pub fn get_label(&self, level: f64) -> String {
+        format!("{}", (level*1e6).round()/1e6+0.0)
+    }
+
+    // The lines of every level, see math::contour_lines
+    pub fn get_lines(&self) -> Vec<(f64, Contours)> {
+        self.levels.iter().map(|&level| (level, contour_lines(&self.x, &self.y, &self.z, level))).collect()
+    }
+
+    // The polygons between every pair of neighbouring levels, with the color of
+    // the band. The colors follow the value in the middle of every band, from the
+    // start of the colormap for the lowest band to its end for the highest.
+    pub fn get_bands(&self) -> Vec<(Color, Contours)> {
+        let middles: Vec<f32> = self.levels.windows(2).map(|l| ((l[0]+l[1])*0.5) as f32).collect();
+        let colors = Colormap::normalized(&self.colormap, &middles);
+        self.levels.windows(2).zip(colors).map(|(l, color)| {
+            let (r, g, b) = color.unwrap_or((0.0, 0.0, 0.0));
+            (Color(r, g, b, 1.0), contour_bands(&self.x, &self.y, &self.z, l[0], l[1]))
+        }).collect()
+    }
+
+}
+
+impl Quiver2D {
+
+    pub fn new(x: DMatrix<f32>, y: DMatrix<f32>, u: DMatrix<f32>, v: DMatrix<f32>) -> Self {
+        if [y.shape(), u.shape(), v.shape()].iter().any(|&shape| shape != x.shape()) {
+            panic!("The field needs x, y, u and v of the same shape");
+        }
+        Self {
+            x,
+            y,
+            u,
+            v,
+            color: Color::BLACK,
+            scale: None,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    // The scale that is used, by default the longest arrow is 0.9 times the
+    // smallest distance between the points of the grid
+    pub fn get_scale(&self) -> f64 {
+        if let Some(scale) = self.scale {
+            return scale;
+        }
+        let (rows, cols) = self.x.shape();
+        let mut distance = f64::MAX;
+        for i in 0..rows {
+            for j in 0..cols {
+                for &(k, l) in [(i+1, j), (i, j+1)].iter() {
+                    if k < rows && l < cols {
+                        let (dx, dy) = (self.x[(k, l)]-self.x[(i, j)], self.y[(k, l)]-self.y[(i, j)]);
+                        let d = (dx as f64).hypot(dy as f64);
+                        if d > 0.0 {
+                            distance = distance.min(d);
+                        }
+                    }
+                }
+            }
+        }
+        let longest = self.u.iter().zip(self.v.iter()).map(|(&u, &v)| (u as f64).hypot(v as f64))
+            .filter(|l| l.is_finite()).fold(0.0, f64::max);
+        if longest > 0.0 && distance < f64::MAX {0.9*distance/longest} else {1.0}
+    }
+
+    // The start and the tip of every arrow in data coordinates
+    pub fn get_arrows(&self) -> Vec<[(f64, f64);2]> {
+        let scale = self.get_scale();
+        self.x.iter().zip(self.y.iter()).zip(self.u.iter().zip(self.v.iter()))
+            .filter(|((_, _), (u, v))| u.is_finite() && v.is_finite())
+            .map(|((&x, &y), (&u, &v))| {
+                let (x, y) = (x as f64, y as f64);
+                [(x, y), (x+u as f64*scale, y+v as f64*scale)]
+            }).collect()
+    }
+
+}
+
+impl Stream2D {
+
+    pub fn new(x: DMatrix<f32>, y: DMatrix<f32>, u: DMatrix<f32>, v: DMatrix<f32>) -> Self {
+        if [y.shape(), u.shape(), v.shape()].iter().any(|&shape| shape != x.shape()) {
+            panic!("The field needs x, y, u and v of the same shape");
+        }
+        Self {
+            x,
+            y,
+            u,
+            v,
+            color: Color::BLUE,
+            density: 1.0,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    // How close the lines are, 2.0 gives twice as many lines along each axis
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn get_lines(&self) -> Vec<Vec<(f64, f64)>> {
+        streamlines(&self.x, &self.y, &self.u, &self.v, self.density)
+    }
+
+}
+
+impl Pie {
+
+    pub fn new<T: Into<f64> + Copy>(values: &[T]) -> Self {
+        let values: Vec<f64> = values.iter().map(|&v| v.into()).collect();
+        if values.iter().any(|&v| v.is_nan() || v < 0.0) {
+            panic!("Pie values can't be negative");
+        }
+        Self {
+            title: String::from("Plot"),
+            labels: Vec::new(),
+            colors: (0..values.len()).map(|i| PIE_COLORS[i%PIE_COLORS.len()]).collect(),
+            explode: vec![0.0; values.len()],
+            values,
+            hole: 0.0,
+            start_angle: 90.0,
+            percentages: true,
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_values(&self) -> &Vec<f64> {
+        &self.values
+    }
+
+    pub fn labels(mut self, labels: &[&str]) -> Self {
+        self.labels = labels.iter().map(|&l| l.to_owned()).collect();
+        self
+    }
+
+    // The label of wedge i, wedges without one are left unnamed
+    pub fn get_label(&self, i: usize) -> Option<&str> {
+        self.labels.get(i).map(|l| l.as_str())
+    }
+
+    pub fn colors(mut self, colors: &[Color]) -> Self {
+        for (i, &color) in colors.iter().enumerate().take(self.colors.len()) {
+            self.colors[i] = color;
+        }
+        self
+    }
+
+    pub fn get_colors(&self) -> &Vec<Color> {
+        &self.colors
+    }
+
+    pub fn explode(mut self, explode: &[f64]) -> Self {
+        for (i, &offset) in explode.iter().enumerate().take(self.explode.len()) {
+            self.explode[i] = offset;
+        }
+        self
+    }
+
+    pub fn get_explode(&self) -> &Vec<f64> {
+        &self.explode
+    }
+
+    // Makes a donut with a hole of the given fraction of the radius
+    pub fn hole(mut self, hole: f64) -> Self {
+        if !(0.0..1.0).contains(&hole) {
+            panic!("The hole has to be at least 0 and less than 1");
+        }
+        self.hole = hole;
+        self
+    }
+
+    pub fn get_hole(&self) -> f64 {
+        self.hole
+    }
+
+    pub fn start_angle(mut self, start_angle: f64) -> Self {
+        self.start_angle = start_angle;
+        self
+    }
+
+    pub fn percentages(mut self, percentages: bool) -> Self {
+        self.percentages = percentages;
+        self
+    }
+
+    pub fn has_percentages(&self) -> bool {
+        self.percentages
+    }
+
+    // Fractions of the total that each wedge covers
+    pub fn get_shares(&self) -> Vec<f64> {
+        let total: f64 = self.values.iter().sum();
+        self.values.iter().map(|v| if total > 0.0 {v/total} else {0.0}).collect()
+    }
+
+    // The start and end of each wedge in radians counterclockwise from 3 o'clock
+    pub fn get_angles(&self) -> Vec<(f64, f64)> {
+        let mut start = self.start_angle.to_radians();
+        self.get_shares().iter().map(|share| {
+            let end = start+share*2.0*std::f64::consts::PI;
+            let angles = (start, end);
+            start = end;
+            angles
+        }).collect()
+    }
+
+    pub fn get_percentage(&self, i: usize) -> String {
+        format!("{:.1}%", self.get_shares()[i]*100.0)
+    }
+
+    // Lays out the pie in pixel space for the given size.
+    pub fn scene(&self, width: f32, height: f32) -> Scene {
+        scene::pie(self, width, height)
+    }
+
+    // Rasterizes the pie on the CPU. Returns the pixels row by row as RGBA.
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        raster::rgba(&raster::render(&self.scene(width as f32, height as f32)))
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        let pixmap = raster::render(&self.scene(width as f32, height as f32));
+        raster::save_png(&pixmap, path)
+    }
+
+    pub fn to_svg_string(&self, width: u32, height: u32) -> String {
+        svg::render(&self.scene(width as f32, height as f32))
+    }
+
+    pub fn save_svg<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        svg::save(&self.scene(width as f32, height as f32), path)
+    }
+
+    pub fn save_pdf<P: AsRef<Path>>(&self, path: P, page: PageSize) -> io::Result<()> {
+        let (width, height) = page.pixels();
+        pdf::save(&self.scene(width, height), path)
+    }
+
+    // Opens the pie in a window, like Plot2D::show
+    pub fn show(self) {
+        Window::open(Shown::Pie(self));
+    }
+
+    // Draws the pie in the terminal through the Kitty or Sixel graphics protocol.
+    // Terminals without graphics get the path of a PNG file instead.
+    pub fn show_inline(&self) -> io::Result<()> {
+        let (width, height) = INLINE_SIZE;
+        inline::show_or_save(&self.render_rgba(width, height), width, height, "pie")
+    }
+
+}
+
+// The year, month and day of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days+719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era-day_of_era/1460+day_of_era/36_524-day_of_era/146_096)/365;
+    let day_of_year = day_of_era-(365*year_of_era+year_of_era/4-year_of_era/100);
+    let mp = (5*day_of_year+2)/153;
+    let day = (day_of_year-(153*mp+2)/5+1) as u32;
+    let month = if mp < 10 {mp+3} else {mp-9} as u32;
+    (year_of_era+era*400+if month <= 2 {1} else {0}, month, day)
+}
+
+impl Candlestick {
+
+    pub fn new<T: Into<f64> + Copy>(timestamps: &[i64], open: &[T], high: &[T], low: &[T], close: &[T]) -> Self {
+        let n = timestamps.len();
+        if open.len() != n || high.len() != n || low.len() != n || close.len() != n {
+            panic!("The candles need as many prices of each kind as timestamps");
+        }
+        if timestamps.windows(2).any(|t| t[1] <= t[0]) {
+            panic!("The timestamps of the candles have to increase");
+        }
+        let values = |v: &[T]| v.iter().map(|&x| x.into()).collect::<Vec<f64>>();
+        Self {
+            timestamps: timestamps.to_vec(),
+            open: values(open),
+            high: values(high),
+            low: values(low),
+            close: values(close),
+            volume: None,
+            up_color: Color(0.15, 0.65, 0.3, 1.0),
+            down_color: Color(0.85, 0.2, 0.2, 1.0),
+            width: 0.6,
+            skip_gaps: true,
+        }
+    }
+
+    pub fn volume<T: Into<f64> + Copy>(mut self, volume: &[T]) -> Self {
+        if volume.len() != self.timestamps.len() {
+            panic!("The candles need as many volumes as timestamps");
+        }
+        self.volume = Some(volume.iter().map(|&v| v.into()).collect());
+        self
+    }
+
+    pub fn get_volume(&self) -> Option<&Vec<f64>> {
+        self.volume.as_ref()
+    }
+
+    pub fn up_color(mut self, color: Color) -> Self {
+        self.up_color = color;
+        self
+    }
+
+    pub fn down_color(mut self, color: Color) -> Self {
+        self.down_color = color;
+        self
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn skip_gaps(mut self, skip_gaps: bool) -> Self {
+        self.skip_gaps = skip_gaps;
+        self
+    }
+
+    pub fn get_timestamps(&self) -> &Vec<i64> {
+        &self.timestamps
+    }
+
+    // The open, high, low and close of candle i
+    pub fn get_prices(&self, i: usize) -> [f64;4] {
+        [self.open[i], self.high[i], self.low[i], self.close[i]]
+    }
+
+    pub fn is_up(&self, i: usize) -> bool {
+        self.close[i] >= self.open[i]
+    }
+
+    pub fn get_color(&self, i: usize) -> Color {
+        if self.is_up(i) {self.up_color} else {self.down_color}
+    }
+
+    // Seconds between the closest candles, the room of one candle when the
+    // gaps are kept
+    fn get_step(&self) -> f64 {
+        let step = self.timestamps.windows(2).map(|t| t[1]-t[0]).filter(|&d| d > 0).min();
+        step.unwrap_or(1) as f64
+    }
+
+    // Where the candles go along x, in candles from the first one
+    pub fn get_positions(&self) -> Vec<f64> {
+        if self.skip_gaps {
+            return (0..self.timestamps.len()).map(|i| i as f64).collect();
+        }
+        let (first, step) = (self.timestamps.first().cloned().unwrap_or(0), self.get_step());
+        self.timestamps.iter().map(|&t| (t-first) as f64/step).collect()
+    }
+
+    // The time at the position x, as the date for daily candles and as the time
+    // of day otherwise. Skipped gaps have no time, so the closest candle is used.
+    pub fn get_time_label(&self, x: f64) -> String {
+        if self.timestamps.is_empty() {
+            return String::new();
+        }
+        let t = if self.skip_gaps {
+            let i = x.round().max(0.0).min((self.timestamps.len()-1) as f64) as usize;
+            self.timestamps[i]
+        } else {
+            self.timestamps[0]+(x*self.get_step()).round() as i64
+        };
+        if self.timestamps.iter().all(|t| t%86_400 == 0) {
+            const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct",
+                                         "Nov", "Dec"];
+            let (_, month, day) = civil_from_days(t.div_euclid(86_400));
+            format!("{} {}", MONTHS[month as usize-1], day)
+        } else {
+            let seconds = t.rem_euclid(86_400);
+            format!("{:02}:{:02}", seconds/3600, seconds%3600/60)
+        }
+    }
+
+    // The label of the x tick at x with ticks the spacing apart. With skipped
+    // gaps only candles have times, so every candle is named once at the tick
+    // closest to it and the other ticks get no label.
+    pub fn get_tick_label(&self, x: f64, spacing: f64) -> String {
+        if !self.skip_gaps {
+            return self.get_time_label(x);
+        }
+        let candle = x.round();
+        let offset = x-candle;
+        if candle < 0.0 || candle >= self.timestamps.len() as f64 || offset <= -spacing*0.5 || offset > spacing*0.5 {
+            return String::new();
+        }
+        self.get_time_label(candle)
+    }
+
+}
+
+impl Default for PolarPlot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PolarPlot {
+
+    pub fn new() -> Self {
+        Self {
+            title: String::from("Plot"),
+            lines: Vec::new(),
+            rlim: None,
+            angle_unit: AngleUnit::Degrees,
+            rings: 4,
+        }
+    }
+
+    // A polar plot of the line, the radial axis goes from 0 to its largest r
+    pub fn plot(l: Line2D) -> Self {
+        let mut default = Self::new();
+        default.add_line(l);
+        default
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_lines(&self) -> &Vec<Line2D> {
+        &self.lines
+    }
+
+    pub fn add_line(&mut self, line: Line2D) {
+        self.lines.push(line);
+    }
+
+    pub fn rlim(mut self, rlim: &[f64;2]) -> Self {
+        if rlim[0].partial_cmp(&rlim[1]) != Some(std::cmp::Ordering::Less) {
+            panic!("The radial limits have to be increasing");
+        }
+        self.rlim = Some(*rlim);
+        self
+    }
+
+    pub fn get_rlim(&self) -> [f64;2] {
+        self.rlim.unwrap_or_else(|| {
+            let radii = self.lines.iter().flat_map(|l| l.get_data().iter().map(|p| p.1));
+            let max = radii.filter(|r| r.is_finite()).fold(0.0, f64::max);
+            [0.0, if max > 0.0 {max} else {1.0}]
+        })
+    }
+
+    pub fn rings(mut self, rings: usize) -> Self {
+        self.rings = rings.max(1);
+        self
+    }
+
+    pub fn get_rings(&self) -> usize {
+        self.rings
+    }
+
+    pub fn angle_unit(mut self, angle_unit: AngleUnit) -> Self {
+        self.angle_unit = angle_unit;
+        self
+    }
+
+    pub fn get_angle_unit(&self) -> AngleUnit {
+        self.angle_unit
+    }
+
+    // The angles of the spokes every 30 degrees in radians, with their labels
+    pub fn get_angle_labels(&self) -> Vec<(f64, String)> {
+        (0..12).map(|k| {
+            let label = match self.angle_unit {
+                AngleUnit::Degrees => format!("{}°", k*30),
+                AngleUnit::Radians => {
+                    // k*pi/6 as a reduced fraction
+                    let gcd = [6, 3, 2, 1].iter().find(|&&d| k%d == 0 && 6%d == 0).cloned().unwrap_or(1);
+                    match (k/gcd, 6/gcd) {
+                        (0, _) => String::from("0"),
+                        (1, 1) => String::from("π"),
+                        (n, 1) => format!("{}π", n),
+                        (1, d) => format!("π/{}", d),
+                        (n, d) => format!("{}π/{}", n, d),
+                    }
+                },
+            };
+            (k as f64*std::f64::consts::PI/6.0, label)
+        }).collect()
+    }
+
+    // Lays out the plot in pixel space for the given size.
+    pub fn scene(&self, width: f32, height: f32) -> Scene {
+        scene::polar(self, width, height)
+    }
+
+    // Rasterizes the plot on the CPU. Returns the pixels row by row as RGBA.
+    pub fn render_rgba(&self, width: u32, height: u32) -> Vec<u8> {
+        raster::rgba(&raster::render(&self.scene(width as f32, height as f32)))
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        let pixmap = raster::render(&self.scene(width as f32, height as f32));
+        raster::save_png(&pixmap, path)
+    }
+
+    pub fn to_svg_string(&self, width: u32, height: u32) -> String {
+        svg::render(&self.scene(width as f32, height as f32))
+    }
+
+    pub fn save_svg<P: AsRef<Path>>(&self, path: P, width: u32, height: u32) -> io::Result<()> {
+        svg::save(&self.scene(width as f32, height as f32), path)
+    }
+
+    pub fn save_pdf<P: AsRef<Path>>(&self, path: P, page: PageSize) -> io::Result<()> {
+        let (width, height) = page.pixels();
+        pdf::save(&self.scene(width, height), path)
+    }
+
+    // Opens the plot in a window, like Plot2D::show
+    pub fn show(self) {
+        Window::open(Shown::Polar(self));
+    }
+
+    // Draws the plot in the terminal through the Kitty or Sixel graphics protocol.
+    // Terminals without graphics get the path of a PNG file instead.
+    pub fn show_inline(&self) -> io::Result<()> {
+        let (width, height) = INLINE_SIZE;
+        inline::show_or_save(&self.render_rgba(width, height), width, height, "polar")
+    }
+
+}
+
+impl Heatmap2D {
+
+    pub fn new(data: DMatrix<f32>) -> Self {
+        Self {
+            data,
+            colormap: String::from("hot"),
+            extent: None,
+            edges: None,
+            colorbar: false,
+        }
+    }
+
+    // One of the names Colormap knows, "hot" or "gray"
+    pub fn colormap(mut self, colormap: &str) -> Self {
+        assert!(Colormap::is_known(colormap), "Not a valid colormap: {}", colormap);
+        self.colormap = String::from(colormap);
+        self
+    }
+
+    pub fn extent(mut self, x0: f64, x1: f64, y0: f64, y1: f64) -> Self {
+        self.extent = Some([x0, x1, y0, y1]);
+        self
+    }
+
+    pub fn get_extent(&self) -> [f64;4] {
+        self.extent.unwrap_or([0.0, self.data.ncols() as f64, 0.0, self.data.nrows() as f64])
+    }
+
+    // Outlines every cell with the color
+    pub fn edges(mut self, color: Color) -> Self {
+        self.edges = Some(color);
+        self
+    }
+
+    pub fn get_edges(&self) -> Option<&Color> {
+        self.edges.as_ref()
+    }
+
+    // Shows which values the colors stand for next to the plot
+    pub fn colorbar(mut self) -> Self {
+        self.colorbar = true;
+        self
+    }
+
+    pub fn has_colorbar(&self) -> bool {
+        self.colorbar
+    }
+
+    pub fn get_data(&self) -> &DMatrix<f32> {
+        &self.data
+    }
+
+    // The rectangle [x0, x1, y0, y1] and the color of every cell. Cells that
+    // aren't finite are left out.
+    pub fn get_cells(&self) -> Vec<([f64;4], Color)> {
+        let [x0, x1, y0, y1] = self.get_extent();
+        let (rows, cols) = self.data.shape();
+        let (dx, dy) = ((x1-x0)/cols as f64, (y1-y0)/rows as f64);
+        // The matrix is stored column by column, like the colors
+        let colors = Colormap::normalized(&self.colormap, self.data.as_slice());
+        let mut cells = Vec::with_capacity(rows*cols);
+        for j in 0..cols {
+            for i in 0..rows {
+                if let Some((r, g, b)) = colors[j*rows+i] {
+                    let (x, y) = (x0+j as f64*dx, y0+i as f64*dy);
+                    cells.push(([x, x+dx, y, y+dy], Color(r, g, b, 1.0)));
+                }
+            }
+        }
+        cells
+    }
+
+    // The colors of the colorbar in even steps from the smallest to the largest
+    // finite value, with the value of each. Empty when no value is finite.
+    pub fn get_colorbar(&self, steps: usize) -> Vec<(f32, Color)> {
+        let finite = self.data.iter().copied().filter(|v| v.is_finite());
+        let min = finite.clone().fold(f32::INFINITY, f32::min);
+        let max = finite.fold(f32::NEG_INFINITY, f32::max);
+        if min > max {
+            return Vec::new();
+        }
+        let steps = steps.max(2);
+        (0..steps).map(|k| {
+            let t = k as f32/(steps-1) as f32;
+            let (r, g, b) = Colormap::color(&self.colormap, t);
+            (min+t*(max-min), Color(r, g, b, 1.0))
+        }).collect()
+    }
+
+}
+
+// The groups as sorted values without the ones that aren't finite
+fn sorted_groups<T: Into<f64> + Copy>(groups: &[&[T]]) -> Vec<Vec<f64>> {
+    groups.iter().map(|group| {
+        let mut values: Vec<f64> = group.iter().map(|&v| v.into()).filter(|v| v.is_finite()).collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        values
+    }).collect()
+}
+
+impl Box2D {
+
+    pub fn new<T: Into<f64> + Copy>(groups: &[&[T]]) -> Self {
+        Self {
+            color: Color::BLUE,
+            groups: sorted_groups(groups),
+            labels: (1..=groups.len()).map(|i| i.to_string()).collect(),
+            width: 0.5,
+            whiskers: Whiskers::Iqr,
+            notch: false,
+            legend: None,
+        }
+    }
+
+    // Names the groups instead of numbering them
+    pub fn categories(mut self, labels: &[&str]) -> Self {
+        self.labels = labels.iter().map(|l| l.to_string()).collect();
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn get_width(&self) -> f64 {
+        self.width
+    }
+
+    pub fn whiskers(mut self, whiskers: Whiskers) -> Self {
+        self.whiskers = whiskers;
+        self
+    }
+
+    pub fn get_whiskers(&self) -> Whiskers {
+        self.whiskers
+    }
+
+    // Narrows the box around the median to its confidence interval
+    pub fn notch(mut self) -> Self {
+        self.notch = true;
+        self
+    }
+
+    pub fn is_notched(&self) -> bool {
+        self.notch
+    }
+
+    pub fn get_labels(&self) -> &Vec<String> {
+        &self.labels
+    }
+
+    // The quartiles, whiskers and outliers of every group, none for empty groups
+    // since they get no box.
+    pub fn get_stats(&self) -> Vec<Option<BoxStats>> {
+        self.groups.iter().map(|values| {
+            if values.is_empty() {
+                return None;
+            }
+            let median = percentile(values, 0.5);
+            let q1 = percentile(values, 0.25);
+            let q3 = percentile(values, 0.75);
+            let (low, high) = match self.whiskers {
+                Whiskers::Iqr => (q1-1.5*(q3-q1), q3+1.5*(q3-q1)),
+                Whiskers::Percentile(low, high) => (percentile(values, low/100.0), percentile(values, high/100.0)),
+            };
+            // The whiskers end at the last values inside the limits
+            let inside: Vec<f64> = values.iter().copied().filter(|&v| v >= low && v <= high).collect();
+            let half = 1.57*(q3-q1)/(values.len() as f64).sqrt();
+            Some(BoxStats {
+                median,
+                q1,
+                q3,
+                whisker_low: inside.first().copied().unwrap_or(q1).min(q1),
+                whisker_high: inside.last().copied().unwrap_or(q3).max(q3),
+                outliers: values.iter().copied().filter(|&v| v < low || v > high).collect(),
+                notch: (median-half, median+half),
+            })
+        }).collect()
+    }
+
+    // The outline of the box at x, with the notch cut into its sides
+    pub fn get_outline(&self, x: f64, stats: &BoxStats) -> Vec<(f64, f64)> {
+        let (left, right) = (x-self.width*0.5, x+self.width*0.5);
+        if !self.notch {
+            return vec![(left, stats.q1), (right, stats.q1), (right, stats.q3), (left, stats.q3)];
+        }
+        let inset = self.width*0.25;
+        vec![(left, stats.q1), (right, stats.q1), (right, stats.notch.0), (right-inset, stats.median),
+             (right, stats.notch.1), (right, stats.q3), (left, stats.q3), (left, stats.notch.1),
+             (left+inset, stats.median), (left, stats.notch.0)]
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
+
+}
+
+impl Violin2D {
+
+    pub fn new<T: Into<f64> + Copy>(groups: &[&[T]]) -> Self {
+        Self {
+            color: Color::BLUE,
+            groups: sorted_groups(groups),
+            labels: (1..=groups.len()).map(|i| i.to_string()).collect(),
+            width: 0.8,
+            bandwidth: None,
+            points: 100,
+            legend: None,
+        }
+    }
+
+    // Names the groups instead of numbering them
+    pub fn categories(mut self, labels: &[&str]) -> Self {
+        self.labels = labels.iter().map(|l| l.to_string()).collect();
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    // Width of the kernel in data units, instead of Scott's rule
+    pub fn bandwidth(mut self, bandwidth: f64) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    pub fn get_labels(&self) -> &Vec<String> {
+        &self.labels
+    }
+
+    // The median of every group, none for empty groups
+    pub fn get_medians(&self) -> Vec<Option<f64>> {
+        self.groups.iter().map(|values| if values.is_empty() {None} else {Some(percentile(values, 0.5))}).collect()
+    }
+
+    // The outline of every violin, up one side and down the other. The density
+    // is scaled so the widest point of each violin has the full width.
+    pub fn get_polygons(&self) -> Vec<Vec<(f64, f64)>> {
+        self.groups.iter().enumerate().filter(|(_, values)| !values.is_empty()).map(|(i, values)| {
+            let (low, high) = (values[0], values[values.len()-1]);
+            let ys = Linspace::linspace(low, high, self.points.max(1));
+            let density = kde(values, &ys, self.bandwidth);
+            let max = density.iter().cloned().fold(0.0, f64::max);
+            let half: Vec<f64> = density.iter()
+                .map(|d| if max > 0.0 { d/max*self.width*0.5 } else { self.width*0.5 }).collect();
+            let x = i as f64;
+            let mut polygon: Vec<(f64, f64)> = ys.iter().zip(half.iter()).map(|(&y, &h)| (x+h, y)).collect();
+            polygon.extend(ys.iter().zip(half.iter()).rev().map(|(&y, &h)| (x-h, y)));
+            polygon
+        }).collect()
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
+
+}
+
+impl Hist2D {
+
+    pub fn new<T: Into<f64> + Copy>(data: &[T], bins: Bins) -> Self {
+        let (edges, counts) = histogram(data, &bins);
+        Self {
+            color: Color::BLUE,
+            edges,
+            counts,
+            normalization: Normalization::Count,
+            hist_type: HistType::Filled,
+            legend: None,
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn get_color(&self) -> &Color {
+        &self.color
+    }
+
+    pub fn get_edges(&self) -> &Vec<f64> {
+        &self.edges
+    }
+
+    pub fn get_counts(&self) -> &Vec<usize> {
+        &self.counts
+    }
+
+    pub fn normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn get_normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    pub fn hist_type(mut self, hist_type: HistType) -> Self {
+        self.hist_type = hist_type;
+        self
+    }
+
+    pub fn get_hist_type(&self) -> HistType {
+        self.hist_type
+    }
+
+    // Heights of the bins with the normalization of the histogram
+    pub fn get_values(&self) -> Vec<f64> {
+        let total = self.counts.iter().sum::<usize>().max(1) as f64;
+        match self.normalization {
+            Normalization::Count => self.counts.iter().map(|&c| c as f64).collect(),
+            Normalization::Density => self.counts.iter().zip(self.edges.windows(2))
+                .map(|(&c, edges)| c as f64/total/(edges[1]-edges[0])).collect(),
+            Normalization::Cumulative => self.counts.iter().scan(0, |sum, &c| {
+                *sum += c;
+                Some(*sum as f64)
+            }).collect(),
+        }
+    }
+
+    pub fn legend(mut self, legend: &str) -> Self {
+        self.legend = Some(legend.to_owned());
+        self
+    }
+
+    pub fn get_legend(&self) -> Option<&str> {
+        self.legend.as_deref()
+    }
 
 }
+
 impl Surface3D {
     pub fn new(x: DMatrix<f32>, y: DMatrix<f32>, z: DMatrix<f32>) -> Self {
         Self {
@@ -603,3 +3066,254 @@ impl IntoIterator for Linspace<f64> {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turntables_turn_once_with_valid_frames() {
+        assert!((turntable_azimuth(DEFAULT_ANGLES.1, 0, 4)-DEFAULT_ANGLES.1).abs() < 1e-4);
+        assert!((turntable_azimuth(-116.0, 1, 4)+26.0).abs() < 1e-4);
+        assert!((turntable_azimuth(-116.0, 2, 4)+296.0).abs() < 1e-4);
+        let plot = Plot3D::new();
+        let path = std::env::temp_dir().join(format!("plotting-turntable-{}.gif", std::process::id()));
+        for (frames, fps) in [(0, 10.0), (4, 0.0), (4, f32::NAN)] {
+            let error = plot.save_turntable_gif(&path, frames, fps).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn linestyles_combine_markers_and_lines() {
+        assert_eq!(parse_linestyle("o-"), Ok((Some(Marker::Circle), true)));
+        assert_eq!(parse_linestyle("-"), Ok((None, true)));
+        assert_eq!(parse_linestyle("*"), Ok((Some(Marker::Star), false)));
+    }
+
+    #[test]
+    fn unknown_linestyles_are_errors() {
+        assert_eq!(LineStyle::parse("o-q"), Err("Not a valid linestyle: \"o-q\"".to_string()));
+        assert!(parse_linestyle("").is_err());
+        assert!(parse_linestyle("os").is_err());
+    }
+
+    #[test]
+    fn steps_and_stems_follow_the_style() {
+        let line = || Line2D::new(&[0.0, 1.0, 3.0], &[1.0, 2.0, 0.0]);
+        assert_eq!(line().steps(Step::Pre).get_line_points(),
+                   [(0.0, 1.0), (0.0, 2.0), (1.0, 2.0), (1.0, 0.0), (3.0, 0.0)]);
+        assert_eq!(line().steps(Step::Post).get_line_points(),
+                   [(0.0, 1.0), (1.0, 1.0), (1.0, 2.0), (3.0, 2.0), (3.0, 0.0)]);
+        assert_eq!(line().steps(Step::Mid).get_line_points(),
+                   [(0.0, 1.0), (0.5, 1.0), (0.5, 2.0), (1.0, 2.0), (2.0, 2.0), (2.0, 0.0), (3.0, 0.0)]);
+
+        let stem = line().stem(-1.0);
+        assert_eq!(stem.get_style(), LineStyle{marker: Some(Marker::Circle), draw: DrawStyle::Stem(-1.0)});
+        assert!(stem.get_line_points().is_empty());
+        assert_eq!(stem.get_stems().unwrap().1[1], [(1.0, -1.0), (1.0, 2.0)]);
+        // The baseline is inside the axes
+        assert_eq!(Plot2D::plot(stem).get_axes().get_axes().get_yaxes(), [-1.0, 2.0]);
+    }
+
+    #[test]
+    fn bars_are_grouped_or_stacked() {
+        let mut plot = Plot2D::bar(Bar2D::new(&[1.0, 2.0], &[2.0, -1.0]));
+        plot.add_bar(Bar2D::new(&[1.0, 2.0], &[1.0, -1.0]));
+        assert_eq!(plot.get_bar_rects()[2], (1, [1.0, 1.4, 0.0, 1.0]));
+        // Both ends get 5% of the range as they are away from the baseline
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [-1.15, 2.15]);
+        let mut plot = Plot2D::bar(Bar2D::new(&[1.0, 2.0], &[2.0, -1.0]));
+        plot.add_bar(Bar2D::new(&[1.0, 2.0], &[1.0, -1.0]).mode(BarMode::Stacked));
+        assert_eq!(plot.get_bar_rects()[2], (1, [0.6, 1.4, 2.0, 3.0]));
+        assert_eq!(plot.get_bar_rects()[3], (1, [1.6, 2.4, -1.0, -2.0]));
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [-2.25, 3.25]);
+        // A grouped series starts a new slot, the stacked one after it joins it
+        plot.add_bar(Bar2D::new(&[1.0], &[1.0]));
+        plot.add_bar(Bar2D::new(&[1.0], &[4.0]).mode(BarMode::Stacked));
+        assert_eq!(plot.get_bar_rects()[5], (3, [1.0, 1.4, 1.0, 5.0]));
+        // Bars that only go up keep the baseline at the bottom
+        let plot = Plot2D::bar(Bar2D::new(&[1.0, 2.0], &[1.0, 2.0]));
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [0.0, 2.1]);
+    }
+
+    #[test]
+    fn histograms_count_and_normalize_bins() {
+        let data = [0.0, 0.5, 1.0, 1.5, 2.0, 4.0];
+        assert_eq!(histogram(&data, &Bins::Count(2)), (vec![0.0, 2.0, 4.0], vec![4, 2]));
+        assert_eq!(histogram(&data, &Bins::Edges(vec![1.0, 2.0])).1, vec![3]);
+        assert_eq!(histogram(&data, &Bins::Sturges).1.len(), 4);
+        // Edges are sorted and the ones without a number or repeated are left out
+        assert_eq!(histogram(&data, &Bins::Edges(vec![2.0, f64::NAN, 0.0, 2.0, 1.0])),
+                   (vec![0.0, 1.0, 2.0], vec![2, 3]));
+
+        let mut plot = Plot2D::new();
+        plot.add_hist(Hist2D::new(&data, Bins::Count(2)).normalization(Normalization::Density));
+        assert_eq!(plot.get_hist_rects()[0], (0, [0.0, 2.0, 0.0, 4.0/12.0]));
+        let mut plot = Plot2D::new();
+        plot.add_hist(Hist2D::new(&data, Bins::Count(2)).normalization(Normalization::Cumulative)
+                      .hist_type(HistType::Step));
+        assert_eq!(plot.get_hist_outlines()[0].last(), Some(&(4.0, 0.0)));
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [0.0, 6.0]);
+    }
+
+    #[test]
+    fn errors_widen_the_axes() {
+        let line = Line2D::new(&[0.0, 1.0], &[1.0, 2.0]).yerr_asymmetric(&[0.5, 0.5], &[0.0, 1.0])
+            .xerr(&[0.25, 0.25]);
+        assert_eq!(line.get_error_ranges()[1], [0.75, 1.25, 1.5, 3.0]);
+        let plot = Plot2D::plot(line.error_style(ErrorStyle::Band));
+        assert_eq!(plot.get_axes().get_axes().get_xaxes(), [-0.25, 1.25]);
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [0.5, 3.0]);
+        assert_eq!(plot.get_lines()[0].get_band().unwrap(), vec![(0.0, 1.0), (1.0, 3.0), (1.0, 1.5), (0.0, 0.5)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The line needs as many errors as points")]
+    fn errors_need_one_per_point() {
+        let _ = Line2D::new(&[0.0, 1.0], &[1.0, 2.0]).yerr_asymmetric(&[0.5, 0.5], &[1.0]);
+    }
+
+    #[test]
+    fn fills_are_split_by_the_mask() {
+        let fill = FillBetween::baseline(&[0.0, 1.0, 2.0, 3.0, 4.0], &[1.0, 2.0, 3.0, 2.0, 1.0], 0.0)
+            .mask(&[true, true, false, true, true]);
+        assert_eq!(fill.get_polygons(), vec![vec![(0.0, 0.0), (1.0, 0.0), (1.0, 2.0), (0.0, 1.0)],
+                                             vec![(3.0, 0.0), (4.0, 0.0), (4.0, 1.0), (3.0, 2.0)]]);
+
+        let plot = Plot2D::stacked_area(&[0.0, 1.0], &[&[1.0, 2.0], &[3.0, 1.0]], &[Color::RED]);
+        assert_eq!(plot.get_fills()[1].get_polygons()[0][2], (1.0, 3.0));
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [0.0, 4.0]);
+    }
+
+    #[test]
+    fn boxes_have_quartiles_whiskers_and_outliers() {
+        let boxes = Box2D::new(&[&[1.0, 2.0, 3.0, 4.0, 5.0, 20.0][..]]);
+        let stats = boxes.get_stats()[0].clone().unwrap();
+        assert_eq!((stats.q1, stats.median, stats.q3), (2.25, 3.5, 4.75));
+        assert_eq!((stats.whisker_low, stats.whisker_high), (1.0, 5.0));
+        assert_eq!(stats.outliers, vec![20.0]);
+        let stats = boxes.whiskers(Whiskers::Percentile(0.0, 100.0)).get_stats()[0].clone().unwrap();
+        assert_eq!((stats.whisker_high, stats.outliers.len()), (20.0, 0));
+
+        let plot = Plot2D::violinplot(&[&[1.0, 2.0, 2.0, 3.0][..], &[5.0, 6.0][..]]);
+        assert_eq!(plot.get_categories(), Some((&vec!["1".to_string(), "2".to_string()], false)));
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [1.0, 6.0]);
+
+        // Empty groups keep their slot but get no box, median or room on the axes
+        let plot = Plot2D::boxplot(&[&[5.0, 6.0, 7.0][..], &[][..]]);
+        assert_eq!(plot.get_boxes()[0].get_stats()[1], None);
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [5.0, 7.0]);
+        let violins = Violin2D::new(&[&[][..], &[1.0, 3.0][..]]);
+        assert_eq!(violins.get_medians(), vec![None, Some(2.0)]);
+    }
+
+    #[test]
+    fn heatmap_cells_follow_the_matrix() {
+        let data = DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let heatmap = Heatmap2D::new(data).extent(0.0, 3.0, 0.0, 1.0);
+        let cells = heatmap.get_cells();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[1].0, [0.0, 1.0, 0.5, 1.0]);
+        // The largest value is at the top of the colorbar
+        let colorbar = heatmap.get_colorbar(2);
+        assert_eq!((colorbar[0].0, colorbar[1].0), (1.0, 6.0));
+        assert_eq!(cells[5].1, colorbar[1].1);
+
+        // The colors follow the values, a NaN cell is left out
+        let data = DMatrix::from_row_slice(1, 4, &[0.0, f32::NAN, 1.0, 4.0]);
+        let heatmap = Heatmap2D::new(data).colormap("gray");
+        let cells = heatmap.get_cells();
+        assert_eq!(cells.iter().map(|c| c.0[0]).collect::<Vec<_>>(), vec![0.0, 2.0, 3.0]);
+        assert_eq!(cells[1].1, Color(0.25, 0.25, 0.25, 1.0));
+        let colorbar = heatmap.get_colorbar(5);
+        assert_eq!(colorbar.iter().map(|c| c.0).collect::<Vec<_>>(), vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(colorbar[4].1, Color(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a valid colormap")]
+    fn unknown_colormaps_panic() {
+        Heatmap2D::new(DMatrix::zeros(1, 1)).colormap("jet");
+    }
+
+    #[test]
+    fn contour_levels_are_sorted_and_colored() {
+        let (x, y) = meshgrid(&[-1.0, 0.0, 1.0], &[-1.0, 0.0, 1.0]);
+        let z = DMatrix::from_row_slice(3, 3, &[0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0]);
+        // NaN levels are left out, the bands go from the start to the end of the colormap
+        let contour = Contour2D::new(x, y, z, &[2.0, f64::NAN, 0.0, 1.0]).filled().colormap("gray");
+        assert_eq!(contour.get_lines().iter().map(|l| l.0).collect::<Vec<_>>(), vec![0.0, 1.0, 2.0]);
+        let colors: Vec<Color> = contour.get_bands().into_iter().map(|b| b.0).collect();
+        assert_eq!(colors, vec![Color(0.0, 0.0, 0.0, 1.0), Color(1.0, 1.0, 1.0, 1.0)]);
+    }
+
+    #[test]
+    fn quivers_scale_the_arrows() {
+        let (x, y) = meshgrid(&[0.0, 1.0, 2.0], &[0.0, 1.0, 2.0]);
+        let u = DMatrix::from_element(3, 3, 2.0);
+        let v = DMatrix::from_element(3, 3, 0.0);
+        let plot = Plot2D::quiver(x, y, u, v);
+        // The longest arrow is scaled to 0.9 of the grid spacing
+        assert_eq!(plot.get_quivers()[0].get_arrows()[4], [(1.0, 1.0), (1.9, 1.0)]);
+        assert_eq!(plot.get_axes().get_axes().get_xaxes(), [0.0, 2.9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The field needs x, y, u and v of the same shape")]
+    fn vector_fields_need_one_shape() {
+        let (x, y) = meshgrid(&[0.0, 1.0, 2.0], &[0.0, 1.0]);
+        Quiver2D::new(x, y, DMatrix::zeros(2, 3), DMatrix::zeros(3, 2));
+    }
+
+    #[test]
+    fn pie_wedges_follow_the_shares() {
+        let pie = Pie::new(&[1.0, 3.0]).start_angle(0.0);
+        let angles = pie.get_angles();
+        assert_eq!(angles[0], (0.0, std::f64::consts::FRAC_PI_2));
+        assert_eq!(angles[1].1, 2.0*std::f64::consts::PI);
+        assert_eq!(pie.get_percentage(1), "75.0%");
+    }
+
+    #[test]
+    fn polar_plots_have_angles_and_rings() {
+        let theta: Vec<f64> = (0..=8).map(|i| i as f64*std::f64::consts::PI/4.0).collect();
+        let plot = PolarPlot::plot(Line2D::new(&theta, &[1.0, 2.0, 3.0, 2.0, 1.0, 2.0, 3.0, 2.0, 1.0]))
+            .angle_unit(AngleUnit::Radians);
+        assert_eq!(plot.get_rlim(), [0.0, 3.0]);
+        let labels: Vec<String> = plot.get_angle_labels().into_iter().map(|(_, l)| l).collect();
+        assert_eq!(labels[..7], ["0", "π/6", "π/3", "π/2", "2π/3", "5π/6", "π"]);
+
+        // Limits that were set are kept when lines are added
+        let mut plot = PolarPlot::new().rlim(&[0.0, 2.0]);
+        plot.add_line(Line2D::new(&[0.0, 1.0], &[1.0, 5.0]));
+        assert_eq!(plot.get_rlim(), [0.0, 2.0]);
+    }
+
+    #[test]
+    fn candles_skip_gaps() {
+        // Thursday 2024-02-29, Friday and the Monday after them
+        let days = [1_709_164_800, 1_709_251_200, 1_709_510_400];
+        let candles = || Candlestick::new(&days, &[10.0, 12.0, 11.0], &[13.0, 12.5, 11.5], &[9.0, 8.0, 10.0],
+                                          &[12.0, 9.0, 11.0]);
+        assert_eq!(candles().get_positions(), [0.0, 1.0, 2.0]);
+        assert_eq!(candles().skip_gaps(false).get_positions(), [0.0, 1.0, 4.0]);
+        assert_eq!(candles().get_time_label(2.0), "Mar 4");
+        assert_eq!(candles().skip_gaps(false).get_time_label(2.0), "Mar 2");
+        assert_eq!(candles().down_color(Color::RED).get_color(1), Color::RED);
+        assert!(candles().is_up(2));
+
+        let plot = Plot2D::candlestick(candles());
+        assert_eq!(plot.get_axes().get_axes().get_xaxes(), [-0.5, 2.5]);
+        assert_eq!(plot.get_axes().get_axes().get_yaxes(), [8.0, 13.0]);
+        // Every candle is named once, at the tick closest to it
+        let labels: Vec<String> = (0..7).map(|i| plot.get_time_label(-0.5+i as f64*0.5, 0.5).unwrap()).collect();
+        assert_eq!(labels, ["", "Feb 29", "", "Mar 1", "", "Mar 4", ""]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The timestamps of the candles have to increase")]
+    fn candles_need_increasing_timestamps() {
+        Candlestick::new(&[2, 1], &[1.0, 1.0], &[1.0, 1.0], &[1.0, 1.0], &[1.0, 1.0]);
+    }
+}
@@ -0,0 +1,167 @@
+use tiny_skia::{Pixmap, Paint, PathBuilder, Stroke, LineCap, LineJoin, FillRule, Transform, Mask};
+use crate::font;
+use crate::plot::Color;
+use crate::scene::{self, Scene, Primitive, Segment, HAlign, VAlign};
+
+use std::io;
+
+// Software rasterizer for the scenes. Everything is drawn on the CPU so it works
+// without a window or a GPU.
+
+fn paint(color: &Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8((color.0*255.0) as u8, (color.1*255.0) as u8,
+                          (color.2*255.0) as u8, (color.3*255.0) as u8);
+    paint.anti_alias = true;
+    paint
+}
+
+fn stroke(width: f32) -> Stroke {
+    Stroke{width, line_cap: LineCap::Butt, line_join: LineJoin::Miter, ..Stroke::default()}
+}
+
+fn build_path(path: &scene::Path) -> Option<tiny_skia::Path> {
+    let mut builder = PathBuilder::new();
+    for segment in path.segments.iter() {
+        match *segment {
+            Segment::MoveTo(x, y) => builder.move_to(x, y),
+            Segment::LineTo(x, y) => builder.line_to(x, y),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => builder.cubic_to(x1, y1, x2, y2, x, y),
+            Segment::Close => builder.close(),
+        }
+    }
+    builder.finish()
+}
+
+fn fill_text(pixmap: &mut Pixmap, text: &scene::Text, mask: Option<&Mask>) {
+    let width = font::text_width(&text.content, text.size);
+    let (ascent, descent) = font::vertical_metrics(text.size);
+    let x = match text.horizontal_alignment {
+        HAlign::Left => text.position.0,
+        HAlign::Center => text.position.0-width*0.5,
+        HAlign::Right => text.position.0-width,
+    };
+    // Move the position to the baseline of the text
+    let y = match text.vertical_alignment {
+        VAlign::Top => text.position.1+ascent,
+        VAlign::Center => text.position.1+(ascent+descent)*0.5,
+        VAlign::Bottom => text.position.1+descent,
+    };
+    let mut builder = PathBuilder::new();
+    for segment in font::text_outline(&text.content, text.size, x, y) {
+        match segment {
+            font::Segment::MoveTo(x, y) => builder.move_to(x, y),
+            font::Segment::LineTo(x, y) => builder.line_to(x, y),
+            font::Segment::QuadTo(x1, y1, x, y) => builder.quad_to(x1, y1, x, y),
+            font::Segment::CubicTo(x1, y1, x2, y2, x, y) => builder.cubic_to(x1, y1, x2, y2, x, y),
+            font::Segment::Close => builder.close(),
+        }
+    }
+    if let Some(path) = builder.finish() {
+        pixmap.fill_path(&path, &paint(&text.color), FillRule::Winding, Transform::identity(), mask);
+    }
+}
+
+fn draw_primitives(pixmap: &mut Pixmap, primitives: &[Primitive], mask: Option<&Mask>) {
+    for primitive in primitives {
+        match primitive {
+            Primitive::Stroke{path, color, width} => {
+                if let Some(path) = build_path(path) {
+                    pixmap.stroke_path(&path, &paint(color), &stroke(*width), Transform::identity(), mask);
+                }
+            },
+            Primitive::Fill{path, color} => {
+                if let Some(path) = build_path(path) {
+                    pixmap.fill_path(&path, &paint(color), FillRule::Winding, Transform::identity(), mask);
+                }
+            },
+            Primitive::Text(text) => fill_text(pixmap, text, mask),
+            Primitive::Clip{rect, primitives} => {
+                let mut clip = match mask {
+                    Some(mask) => mask.clone(),
+                    None => {
+                        let mut clip = Mask::new(pixmap.width(), pixmap.height()).unwrap();
+                        clip.invert();
+                        clip
+                    },
+                };
+                if let Some(rect) = tiny_skia::Rect::from_xywh(rect.x, rect.y, rect.width, rect.height) {
+                    clip.intersect_path(&PathBuilder::from_rect(rect), FillRule::Winding, false,
+                                        Transform::identity());
+                }
+                draw_primitives(pixmap, primitives, Some(&clip));
+            },
+            Primitive::Group{primitives, ..} => draw_primitives(pixmap, primitives, mask),
+        }
+    }
+}
+
+// Draws the scene on a new pixmap of the same size.
+pub fn render(scene: &Scene) -> Pixmap {
+    let mut pixmap = Pixmap::new((scene.width as u32).max(1), (scene.height as u32).max(1)).unwrap();
+    let background = &scene.background;
+    pixmap.fill(tiny_skia::Color::from_rgba(background.0, background.1, background.2, background.3)
+                .unwrap_or(tiny_skia::Color::WHITE));
+    draw_primitives(&mut pixmap, &scene.primitives, None);
+    pixmap
+}
+
+// The pixels of the pixmap row by row as RGBA without premultiplied alpha.
+pub fn rgba(pixmap: &Pixmap) -> Vec<u8> {
+    let mut data = Vec::with_capacity(pixmap.pixels().len()*4);
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        data.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    data
+}
+
+// The opposite of rgba, a pixmap from RGBA pixels without premultiplied alpha.
+pub fn from_rgba(data: &[u8], width: u32, height: u32) -> Pixmap {
+    let mut pixmap = Pixmap::new(width.max(1), height.max(1)).unwrap();
+    for (pixel, color) in pixmap.pixels_mut().iter_mut().zip(data.chunks(4)) {
+        *pixel = tiny_skia::ColorU8::from_rgba(color[0], color[1], color[2], color[3]).premultiply();
+    }
+    pixmap
+}
+
+pub fn save_png<P: AsRef<std::path::Path>>(pixmap: &Pixmap, path: P) -> io::Result<()> {
+    pixmap.save_png(path).map_err(io::Error::other)
+}
+
+// Writes RGBA frames of the same size as an animated GIF that loops forever.
+pub fn save_gif<P: AsRef<std::path::Path>>(frames: &[Vec<u8>], width: u32, height: u32, fps: f32,
+                                           path: P) -> io::Result<()> {
+    let to_io = |e: gif::EncodingError| io::Error::other(e);
+    let file = std::fs::File::create(path)?;
+    let mut encoder = gif::Encoder::new(io::BufWriter::new(file), width as u16, height as u16, &[])
+        .map_err(to_io)?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(to_io)?;
+    // GIF delays are in hundredths of a second, and viewers slow down anything below two
+    let delay = (100.0/fps).round().max(2.0) as u16;
+    for data in frames {
+        let mut data = data.clone();
+        let mut frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut data, 10);
+        frame.delay = delay;
+        encoder.write_frame(&frame).map_err(to_io)?;
+    }
+    Ok(())
+}
+
+pub fn load_png<P: AsRef<std::path::Path>>(path: P) -> io::Result<Pixmap> {
+    Pixmap::load_png(path).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot::*;
+
+    #[test]
+    fn render_rgba_has_a_pixel_per_point() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]));
+        let data = plot.render_rgba(64, 48);
+        assert_eq!(data.len(), 64*48*4);
+        // The corner is outside the axes, so it keeps the white background
+        assert_eq!(&data[0..4], &[255, 255, 255, 255]);
+    }
+}
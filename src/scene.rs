@@ -0,0 +1,1471 @@
+use nalgebra::base::Vector3;
+use std::cmp::Ordering;
+use crate::math::*;
+use crate::plot::*;
+
+// A backend independent description of a rendered plot. Everything is in pixel
+// space with the origin in the top left corner and y growing downwards. The
+// iced windows and the file exporters all draw from the same scene.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VAlign {
+    Top,
+    Center,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub content: String,
+    pub position: (f32, f32),
+    pub size: f32,
+    pub color: Color,
+    pub horizontal_alignment: HAlign,
+    pub vertical_alignment: VAlign,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Stroke {
+        path: Path,
+        color: Color,
+        width: f32,
+    },
+    Fill {
+        path: Path,
+        color: Color,
+    },
+    Text(Text),
+    // The primitives are only visible inside the rectangle
+    Clip {
+        rect: Rect,
+        primitives: Vec<Primitive>,
+    },
+    // Primitives that belong together, e.g. one line of a plot. Backends that
+    // can't name things draw them like any other primitives.
+    Group {
+        name: String,
+        primitives: Vec<Primitive>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub width: f32,
+    pub height: f32,
+    pub background: Color,
+    pub primitives: Vec<Primitive>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.segments.push(Segment::MoveTo(x, y));
+    }
+
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        self.segments.push(Segment::LineTo(x, y));
+    }
+
+    pub fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.segments.push(Segment::CubicTo(x1, y1, x2, y2, x, y));
+    }
+
+    pub fn close(&mut self) {
+        self.segments.push(Segment::Close);
+    }
+
+    // Approximates the circle with four cubic bezier curves
+    pub fn circle(&mut self, x: f32, y: f32, r: f32) {
+        let k = 0.552_284_8*r;
+        self.move_to(x+r, y);
+        self.cubic_to(x+r, y+k, x+k, y+r, x, y+r);
+        self.cubic_to(x-k, y+r, x-r, y+k, x-r, y);
+        self.cubic_to(x-r, y-k, x-k, y-r, x, y-r);
+        self.cubic_to(x+k, y-r, x+r, y-k, x+r, y);
+        self.close();
+    }
+
+    pub fn polygon(&mut self, points: &[(f32, f32)]) {
+        if let Some(&(x, y)) = points.first() {
+            self.move_to(x, y);
+            for &(x, y) in points[1..].iter() {
+                self.line_to(x, y);
+            }
+            self.close();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    // The corners of every subpath with the curves split into straight pieces,
+    // and whether the subpath is closed
+    pub fn flatten(&self) -> Vec<(Vec<(f32, f32)>, bool)> {
+        let mut subpaths: Vec<(Vec<(f32, f32)>, bool)> = Vec::new();
+        let mut current = (0.0, 0.0);
+        for segment in self.segments.iter() {
+            match *segment {
+                Segment::MoveTo(x, y) => subpaths.push((vec![(x, y)], false)),
+                Segment::LineTo(x, y) => {
+                    if subpaths.is_empty() || subpaths.last().unwrap().1 {
+                        subpaths.push((vec![current], false));
+                    }
+                    subpaths.last_mut().unwrap().0.push((x, y));
+                },
+                Segment::CubicTo(x1, y1, x2, y2, x, y) => {
+                    if subpaths.is_empty() || subpaths.last().unwrap().1 {
+                        subpaths.push((vec![current], false));
+                    }
+                    let (x0, y0) = current;
+                    let points = &mut subpaths.last_mut().unwrap().0;
+                    for k in 1..=16 {
+                        let t = k as f32/16.0;
+                        let u = 1.0-t;
+                        let (a, b, c, d) = (u*u*u, 3.0*u*u*t, 3.0*u*t*t, t*t*t);
+                        points.push((a*x0+b*x1+c*x2+d*x, a*y0+b*y1+c*y2+d*y));
+                    }
+                },
+                Segment::Close => {
+                    if let Some(subpath) = subpaths.last_mut() {
+                        subpath.1 = true;
+                        current = subpath.0[0];
+                    }
+                    continue;
+                },
+            }
+            current = *subpaths.last().unwrap().0.last().unwrap();
+        }
+        subpaths
+    }
+}
+
+impl Rect {
+    pub fn contains(&self, p: (f32, f32)) -> bool {
+        p.0 >= self.x && p.0 <= self.x+self.width && p.1 >= self.y && p.1 <= self.y+self.height
+    }
+
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = (self.x+self.width).min(other.x+other.width);
+        let bottom = (self.y+self.height).min(other.y+other.height);
+        Rect{x, y, width: (right-x).max(0.0), height: (bottom-y).max(0.0)}
+    }
+
+    // The part of the line from a to b inside the rectangle (Liang-Barsky)
+    fn clip_segment(&self, a: (f32, f32), b: (f32, f32)) -> Option<((f32, f32), (f32, f32))> {
+        let (dx, dy) = (b.0-a.0, b.1-a.1);
+        let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+        let limits = [(-dx, a.0-self.x), (dx, self.x+self.width-a.0), (-dy, a.1-self.y),
+                      (dy, self.y+self.height-a.1)];
+        for &(p, q) in limits.iter() {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q/p);
+            } else {
+                t1 = t1.min(q/p);
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+        Some(((a.0+t0*dx, a.1+t0*dy), (a.0+t1*dx, a.1+t1*dy)))
+    }
+
+    // The parts of the outline of the path that are inside the rectangle, for
+    // backends that can't clip
+    pub fn clip_stroke(&self, path: &Path) -> Path {
+        let mut clipped = Path::new();
+        for (mut points, closed) in path.flatten() {
+            if closed {
+                points.push(points[0]);
+            }
+            let mut pen = None;
+            for pair in points.windows(2) {
+                if let Some((start, end)) = self.clip_segment(pair[0], pair[1]) {
+                    if pen != Some(start) {
+                        clipped.move_to(start.0, start.1);
+                    }
+                    clipped.line_to(end.0, end.1);
+                    pen = Some(end);
+                }
+            }
+        }
+        clipped
+    }
+
+    // The area of the path that is inside the rectangle, every subpath is cut
+    // by the four edges in turn (Sutherland-Hodgman)
+    pub fn clip_fill(&self, path: &Path) -> Path {
+        let mut clipped = Path::new();
+        let (left, right, top, bottom) = (self.x, self.x+self.width, self.y, self.y+self.height);
+        for (points, _) in path.flatten() {
+            let mut polygon = points;
+            // The signed distance inside each edge, and where a side crosses it
+            let edges: [&dyn Fn((f32, f32)) -> f32; 4] = [&|p| p.0-left, &|p| right-p.0, &|p| p.1-top,
+                                                          &|p| bottom-p.1];
+            for inside in edges.iter() {
+                let mut next = Vec::new();
+                for (k, &b) in polygon.iter().enumerate() {
+                    let a = polygon[(k+polygon.len()-1)%polygon.len()];
+                    let (da, db) = (inside(a), inside(b));
+                    if (da >= 0.0) != (db >= 0.0) {
+                        let t = da/(da-db);
+                        next.push((a.0+(b.0-a.0)*t, a.1+(b.1-a.1)*t));
+                    }
+                    if db >= 0.0 {
+                        next.push(b);
+                    }
+                }
+                polygon = next;
+                if polygon.is_empty() {
+                    break;
+                }
+            }
+            if polygon.len() >= 3 {
+                clipped.polygon(&polygon);
+            }
+        }
+        clipped
+    }
+}
+
+impl Text {
+    pub fn new(content: &str, position: (f32, f32)) -> Self {
+        Self {
+            content: content.to_owned(),
+            position,
+            size: 16.0,
+            color: Color::BLACK,
+            horizontal_alignment: HAlign::Left,
+            vertical_alignment: VAlign::Top,
+        }
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn align(mut self, horizontal_alignment: HAlign, vertical_alignment: VAlign) -> Self {
+        self.horizontal_alignment = horizontal_alignment;
+        self.vertical_alignment = vertical_alignment;
+        self
+    }
+}
+
+impl Scene {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            width,
+            height,
+            background: Color(1.0, 1.0, 1.0, 1.0),
+            primitives: Vec::new(),
+        }
+    }
+
+    pub fn stroke(&mut self, path: Path, color: Color, width: f32) {
+        if !path.is_empty() {
+            self.primitives.push(Primitive::Stroke{path, color, width});
+        }
+    }
+
+    pub fn fill(&mut self, path: Path, color: Color) {
+        if !path.is_empty() {
+            self.primitives.push(Primitive::Fill{path, color});
+        }
+    }
+
+    pub fn text(&mut self, text: Text) {
+        self.primitives.push(Primitive::Text(text));
+    }
+
+    pub fn clip(&mut self, rect: Rect, scene: Scene) {
+        self.primitives.push(Primitive::Clip{rect, primitives: scene.primitives});
+    }
+
+    pub fn group(&mut self, name: &str, scene: Scene) {
+        self.primitives.push(Primitive::Group{name: name.to_owned(), primitives: scene.primitives});
+    }
+}
+
+// Number of decimals needed to show values that are spacing apart
+pub fn decimals(spacing: f64) -> usize {
+    let mut nbr_of_digits = 0;
+    for i in 0..f32::DIGITS {
+        let temp = spacing.fract()*(10.0_f64.powi(i as i32));
+        if temp.fract() < f64::EPSILON {
+            break;
+        }
+        nbr_of_digits += 1;
+    }
+    nbr_of_digits
+}
+
+// Adds the shape of a marker centered at (x, y) to the path. The size is the
+// diameter of the marker, the point marker is a third of it.
+pub fn marker_path(path: &mut Path, marker: Marker, x: f32, y: f32, size: f32) {
+    let r = size*0.5;
+    match marker {
+        Marker::Point => path.circle(x, y, r/3.0),
+        Marker::Circle => path.circle(x, y, r),
+        Marker::Square => path.polygon(&[(x-r, y-r), (x+r, y-r), (x+r, y+r), (x-r, y+r)]),
+        Marker::Triangle => path.polygon(&[(x, y-r), (x+r*0.866, y+r*0.5), (x-r*0.866, y+r*0.5)]),
+        Marker::Diamond => path.polygon(&[(x, y-r), (x+r*0.7, y), (x, y+r), (x-r*0.7, y)]),
+        Marker::Cross => {
+            let d = r*0.707;
+            path.move_to(x-d, y-d);
+            path.line_to(x+d, y+d);
+            path.move_to(x-d, y+d);
+            path.line_to(x+d, y-d);
+        },
+        Marker::Plus => {
+            path.move_to(x-r, y);
+            path.line_to(x+r, y);
+            path.move_to(x, y-r);
+            path.line_to(x, y+r);
+        },
+        Marker::Star => {
+            // Five points with the inner corners at 40% of the radius
+            let points: Vec<(f32, f32)> = (0..10).map(|i| {
+                let angle = std::f32::consts::PI*(i as f32/5.0-0.5);
+                let radius = if i%2 == 0 {r} else {r*0.4};
+                (x+radius*angle.cos(), y+radius*angle.sin())
+            }).collect();
+            path.polygon(&points);
+        },
+    }
+}
+
+// Space between the edge of the window and the plot area of 2D plots
+pub const EDGE: f32 = 30.0;
+
+// Room on the right side of the plot area for a colorbar with its values
+pub fn colorbar_width(plot: &Plot2D) -> f32 {
+    if plot.get_heatmaps().iter().any(|h| h.has_colorbar()) {80.0} else {0.0}
+}
+
+// Room at the bottom for the volume bars of candlesticks
+pub fn volume_height(plot: &Plot2D, height: f32) -> f32 {
+    if plot.get_candlesticks().iter().any(|c| c.get_volume().is_some()) {height*0.25} else {0.0}
+}
+
+// Maps data coordinates of a 2D plot to pixels
+pub struct Transform2D {
+    xlims: [f64;2],
+    ylims: [f64;2],
+    area: Rect,
+}
+
+impl Transform2D {
+    pub fn new(plot: &Plot2D, width: f32, height: f32) -> Self {
+        let width = width-colorbar_width(plot);
+        let height = height-volume_height(plot, height);
+        Self {
+            xlims: plot.get_axes().get_axes().get_xaxes(),
+            ylims: plot.get_axes().get_axes().get_yaxes(),
+            area: Rect{x: EDGE, y: EDGE, width: width-2.0*EDGE, height: height-2.0*EDGE},
+        }
+    }
+
+    pub fn x(&self, x: f64) -> f32 {
+        self.area.x+((x-self.xlims[0])/(self.xlims[1]-self.xlims[0])) as f32*self.area.width
+    }
+
+    pub fn y(&self, y: f64) -> f32 {
+        self.area.y+self.area.height
+            -((y-self.ylims[0])/(self.ylims[1]-self.ylims[0])) as f32*self.area.height
+    }
+
+    pub fn point(&self, p: (f64, f64)) -> (f32, f32) {
+        (self.x(p.0), self.y(p.1))
+    }
+
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+// The parts of a line before and after a gap, and the point in the middle of it
+type Split = (Vec<(f32, f32)>, Vec<(f32, f32)>, (f32, f32));
+
+// Splits the line where it is more than from and less than to along it, and
+// returns the parts before and after with the point in the middle of the gap.
+fn split_line(points: &[(f32, f32)], from: f32, to: f32) -> Split {
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    let mut middle = points[0];
+    let mut length = 0.0;
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let step = ((b.0-a.0).powi(2)+(b.1-a.1).powi(2)).sqrt();
+        let at = |distance: f32| {
+            let t = if step > 0.0 {(distance-length)/step} else {0.0};
+            (a.0+(b.0-a.0)*t, a.1+(b.1-a.1)*t)
+        };
+        if length < from {
+            before.push(a);
+            if length+step >= from {
+                before.push(at(from));
+            }
+        }
+        if length <= (from+to)*0.5 && length+step > (from+to)*0.5 {
+            middle = at((from+to)*0.5);
+        }
+        if length+step > to {
+            after.push(if length >= to {a} else {at(to)});
+        }
+        length += step;
+    }
+    if length > to {
+        after.push(points[points.len()-1]);
+    }
+    (before, after, middle)
+}
+
+// Adds an arrow head with its tip at the point, pointing from the point from
+fn arrow_head(path: &mut Path, from: (f32, f32), tip: (f32, f32), size: f32) {
+    let (dx, dy) = (tip.0-from.0, tip.1-from.1);
+    let length = (dx*dx+dy*dy).sqrt();
+    if length == 0.0 {
+        return;
+    }
+    let (ux, uy) = (dx/length*size, dy/length*size);
+    path.polygon(&[tip, (tip.0-ux-uy*0.4, tip.1-uy+ux*0.4), (tip.0-ux+uy*0.4, tip.1-uy-ux*0.4)]);
+}
+
+fn line_path(path: &mut Path, points: &[(f32, f32)]) {
+    for (i, &(x, y)) in points.iter().enumerate() {
+        if i == 0 {
+            path.move_to(x, y);
+        } else {
+            path.line_to(x, y);
+        }
+    }
+}
+
+pub fn plot2d(plot: &Plot2D, width: f32, height: f32) -> Scene {
+    let mut scene = Scene::new(width, height);
+    let mut texts = Vec::new();
+    let transform = Transform2D::new(plot, width, height);
+    // Everything but the colorbar is laid out in what is left of the width
+    let width = width-colorbar_width(plot);
+    let full_height = height;
+    let height = height-volume_height(plot, height);
+    let xlims = plot.get_axes().get_axes().get_xaxes();
+    let ylims = plot.get_axes().get_axes().get_yaxes();
+    let edge = EDGE;
+
+    // Categorical series are named on their axis instead of numbered
+    let categories = plot.get_categories();
+    let x_categorical = categories.is_some_and(|(_, horizontal)| !horizontal);
+    let y_categorical = categories.is_some_and(|(_, horizontal)| horizontal);
+
+    // The axes cross at the origin, or at the closest limit if the origin is outside.
+    // A categorical axis has no origin so the other axis goes at its start.
+    let x_origin = if x_categorical {
+        transform.x(xlims[0])
+    } else {
+        transform.x(0.0_f64.max(xlims[0]).min(xlims[1]))
+    };
+    let y_origin = if y_categorical {
+        transform.y(ylims[0])
+    } else {
+        transform.y(0.0_f64.max(ylims[0]).min(ylims[1]))
+    };
+
+    // Draw the x and y axes with small arrow heads
+    let mut axes = Path::new();
+    axes.move_to(edge, y_origin);
+    axes.line_to(width-edge, y_origin);
+    axes.line_to(width-edge-1.0, y_origin-1.0);
+    axes.move_to(width-edge, y_origin);
+    axes.line_to(width-edge-1.0, y_origin+1.0);
+    axes.move_to(x_origin, height-edge);
+    axes.line_to(x_origin, edge);
+    axes.line_to(x_origin-1.0, edge+1.0);
+    axes.move_to(x_origin, edge);
+    axes.line_to(x_origin+1.0, edge+1.0);
+    scene.stroke(axes, Color::BLACK, 2.0);
+
+    let grid = plot.get_axes();
+    let nbr_of_points = grid.get_axes().get_nvalues();
+    let x_spacing = (xlims[1]-xlims[0])/(nbr_of_points as f64);
+    let y_spacing = (ylims[1]-ylims[0])/(nbr_of_points as f64);
+    let x_step = (width-2.0*edge)/(nbr_of_points as f32);
+    let y_step = (height-2.0*edge)/(nbr_of_points as f32);
+    let nbr_of_x_digits = decimals(x_spacing);
+    let nbr_of_y_digits = decimals(y_spacing);
+    // Times are longer than numbers so they are written smaller
+    let x_size = if plot.get_candlesticks().is_empty() {16.0} else {12.0};
+
+    // Draw the ticks or the grid lines together with the axes values
+    let mut grid_lines = Path::new();
+    for i in 0..=nbr_of_points {
+        let x_pos = edge+i as f32*x_step;
+        let y_pos = height-edge-i as f32*y_step;
+        let x_value = xlims[0]+i as f64*x_spacing;
+        let x_text = plot.get_time_label(x_value, x_spacing)
+            .unwrap_or_else(|| format!("{:.ndigits$}", x_value, ndigits = nbr_of_x_digits));
+        let y_text = format!("{:.ndigits$}", ylims[0]+i as f64*y_spacing, ndigits = nbr_of_y_digits);
+        // Skip the values that would end up on top of the other axis
+        let show_x_text = (x_pos-x_origin).abs() > 0.1*x_step && !x_categorical && !x_text.is_empty();
+        let show_y_text = (y_pos-y_origin).abs() > 0.1*y_step && !y_categorical;
+        match grid.grid.as_str() {
+            "none" => {
+                if !x_categorical {
+                    grid_lines.move_to(x_pos, y_origin-3.0);
+                    grid_lines.line_to(x_pos, y_origin+3.0);
+                }
+                if !y_categorical {
+                    grid_lines.move_to(x_origin-3.0, y_pos);
+                    grid_lines.line_to(x_origin+3.0, y_pos);
+                }
+                if show_x_text {
+                    texts.push(Text::new(&x_text, (x_pos, y_origin+5.0)).size(x_size)
+                               .align(HAlign::Center, VAlign::Top));
+                }
+                if show_y_text {
+                    texts.push(Text::new(&y_text, (x_origin-10.0, y_pos))
+                               .align(HAlign::Right, VAlign::Center));
+                }
+            },
+            "both" => {
+                if !x_categorical {
+                    grid_lines.move_to(x_pos, edge);
+                    grid_lines.line_to(x_pos, height-edge);
+                }
+                if !y_categorical {
+                    grid_lines.move_to(edge, y_pos);
+                    grid_lines.line_to(width-edge, y_pos);
+                }
+                if show_x_text {
+                    texts.push(Text::new(&x_text, (x_pos+2.0, y_origin+5.0)).size(x_size)
+                               .align(HAlign::Left, VAlign::Top));
+                }
+                if show_y_text {
+                    texts.push(Text::new(&y_text, (x_origin-10.0, y_pos+2.0))
+                               .align(HAlign::Right, VAlign::Bottom));
+                }
+            },
+            _ => panic!("Not a valid string"),
+        }
+    }
+    if let Some((labels, horizontal)) = categories {
+        for (i, label) in labels.iter().enumerate() {
+            if horizontal {
+                texts.push(Text::new(label, (x_origin-10.0, transform.y(i as f64)))
+                           .align(HAlign::Right, VAlign::Center));
+            } else {
+                texts.push(Text::new(label, (transform.x(i as f64), y_origin+5.0))
+                           .align(HAlign::Center, VAlign::Top));
+            }
+        }
+    }
+    scene.stroke(grid_lines, Color::BLACK, 1.0);
+
+    // Draw the bars and the lines, clipped to the plot area
+    let mut lines = Scene::new(width, height);
+    let bar_rects = plot.get_bar_rects();
+    let hist_rects = plot.get_hist_rects();
+    let hist_outlines = plot.get_hist_outlines();
+    // The series are drawn in the order they were added, every kind is numbered
+    // on its own in the names of their groups
+    let mut kinds = Vec::new();
+    for series in plot.get_series() {
+        let n = kinds.iter().filter(|&&kind| kind == series.kind()).count();
+        kinds.push(series.kind());
+        let mut series_scene = Scene::new(width, height);
+        match series {
+            Series::Heatmap(heatmap) => {
+                let mut edges = Path::new();
+                for ([x0, x1, y0, y1], color) in heatmap.get_cells() {
+                    let (left, top) = transform.point((x0, y1));
+                    let (right, bottom) = transform.point((x1, y0));
+                    let mut cell = Path::new();
+                    cell.polygon(&[(left, top), (right, top), (right, bottom), (left, bottom)]);
+                    if heatmap.get_edges().is_some() {
+                        edges.polygon(&[(left, top), (right, top), (right, bottom), (left, bottom)]);
+                    }
+                    series_scene.fill(cell, color);
+                }
+                if let Some(color) = heatmap.get_edges() {
+                    series_scene.stroke(edges, *color, 1.0);
+                }
+            },
+            Series::Contour(contour) => {
+                if contour.is_filled() {
+                    // All the cells of a band are one path so no seams show between them
+                    for (color, polygons) in contour.get_bands() {
+                        let mut path = Path::new();
+                        for polygon in polygons {
+                            let points: Vec<(f32, f32)> = polygon.into_iter().map(|p| transform.point(p)).collect();
+                            path.polygon(&points);
+                        }
+                        series_scene.fill(path, color);
+                    }
+                } else {
+                    let mut path = Path::new();
+                    for (level, lines) in contour.get_lines() {
+                        let label = contour.get_label(level);
+                        let label_width = crate::font::text_width(&label, 12.0)+6.0;
+                        for line in lines {
+                            let points: Vec<(f32, f32)> = line.into_iter().map(|p| transform.point(p)).collect();
+                            let length: f32 = points.windows(2)
+                                .map(|p| ((p[1].0-p[0].0).powi(2)+(p[1].1-p[0].1).powi(2)).sqrt()).sum();
+                            // The label goes in a gap in the middle of lines that are long enough
+                            if contour.has_labels() && length > 3.0*label_width {
+                                let (before, after, middle) = split_line(&points, (length-label_width)*0.5,
+                                                                         (length+label_width)*0.5);
+                                line_path(&mut path, &before);
+                                line_path(&mut path, &after);
+                                let area = transform.area();
+                                if middle.0 > area.x && middle.0 < area.x+area.width && middle.1 > area.y
+                                    && middle.1 < area.y+area.height {
+                                    texts.push(Text::new(&label, middle).size(12.0).align(HAlign::Center, VAlign::Center));
+                                }
+                            } else {
+                                line_path(&mut path, &points);
+                            }
+                        }
+                    }
+                    series_scene.stroke(path, *contour.get_color(), 1.0);
+                }
+            },
+            Series::Quiver(quiver) => {
+                let (mut shafts, mut heads) = (Path::new(), Path::new());
+                for [start, tip] in quiver.get_arrows() {
+                    let (start, tip) = (transform.point(start), transform.point(tip));
+                    let length = ((tip.0-start.0).powi(2)+(tip.1-start.1).powi(2)).sqrt();
+                    // Short arrows get smaller heads so they still point somewhere
+                    let size = (0.4*length).min(8.0);
+                    shafts.move_to(start.0, start.1);
+                    shafts.line_to(tip.0, tip.1);
+                    arrow_head(&mut heads, start, tip, size);
+                }
+                series_scene.stroke(shafts, *quiver.get_color(), 1.5);
+                series_scene.fill(heads, *quiver.get_color());
+            },
+            Series::Stream(stream) => {
+                let (mut path, mut heads) = (Path::new(), Path::new());
+                for line in stream.get_lines() {
+                    let points: Vec<(f32, f32)> = line.into_iter().map(|p| transform.point(p)).collect();
+                    line_path(&mut path, &points);
+                    // The head goes in the middle and points along the field
+                    let middle = points.len()/2;
+                    arrow_head(&mut heads, points[middle-1], points[middle], 8.0);
+                }
+                series_scene.stroke(path, *stream.get_color(), 1.2);
+                series_scene.fill(heads, *stream.get_color());
+            },
+            Series::Fill(fill) => {
+                let mut path = Path::new();
+                for polygon in fill.get_polygons() {
+                    let points: Vec<(f32, f32)> = polygon.into_iter().map(|p| transform.point(p)).collect();
+                    path.polygon(&points);
+                }
+                series_scene.fill(path, fill.get_fill_color());
+            },
+            Series::Bar(bar) => {
+                for (_, [x0, x1, y0, y1]) in bar_rects.iter().filter(|(k, _)| *k == n) {
+                    let (left, top) = transform.point((*x0, *y1));
+                    let (right, bottom) = transform.point((*x1, *y0));
+                    let mut path = Path::new();
+                    path.polygon(&[(left, top), (right, top), (right, bottom), (left, bottom)]);
+                    series_scene.fill(path, *bar.get_color());
+                }
+            },
+            Series::Hist(hist) => {
+                let mut path = Path::new();
+                match hist.get_hist_type() {
+                    HistType::Filled => {
+                        for (_, [x0, x1, y0, y1]) in hist_rects.iter().filter(|(k, _)| *k == n) {
+                            let (left, top) = transform.point((*x0, *y1));
+                            let (right, bottom) = transform.point((*x1, *y0));
+                            path.polygon(&[(left, top), (right, top), (right, bottom), (left, bottom)]);
+                        }
+                        series_scene.fill(path, *hist.get_color());
+                    },
+                    HistType::Step => {
+                        for (i, &p) in hist_outlines[n].iter().enumerate() {
+                            let (x, y) = transform.point(p);
+                            if i == 0 {
+                                path.move_to(x, y);
+                            } else {
+                                path.line_to(x, y);
+                            }
+                        }
+                        series_scene.stroke(path, *hist.get_color(), 2.0);
+                    },
+                }
+            },
+            Series::Box(boxes) => {
+                let color = *boxes.get_color();
+                let (mut outlines, mut medians, mut whiskers, mut outliers) = (Path::new(), Path::new(), Path::new(), Path::new());
+                for (i, stats) in boxes.get_stats().iter().enumerate().filter_map(|(i, s)| Some((i, s.as_ref()?))) {
+                    let x = i as f64;
+                    let outline: Vec<(f32, f32)> = boxes.get_outline(x, stats).into_iter()
+                        .map(|p| transform.point(p)).collect();
+                    outlines.polygon(&outline);
+                    // The median spans the box, or the narrow part of a notched one
+                    let inset = if boxes.is_notched() {boxes.get_width()*0.25} else {0.0};
+                    let half = boxes.get_width()*0.5-inset;
+                    medians.move_to(transform.x(x-half), transform.y(stats.median));
+                    medians.line_to(transform.x(x+half), transform.y(stats.median));
+                    let cap = boxes.get_width()*0.25;
+                    for &(end, whisker) in [(stats.q1, stats.whisker_low), (stats.q3, stats.whisker_high)].iter() {
+                        whiskers.move_to(transform.x(x), transform.y(end));
+                        whiskers.line_to(transform.x(x), transform.y(whisker));
+                        whiskers.move_to(transform.x(x-cap), transform.y(whisker));
+                        whiskers.line_to(transform.x(x+cap), transform.y(whisker));
+                    }
+                    for &outlier in stats.outliers.iter() {
+                        marker_path(&mut outliers, Marker::Circle, transform.x(x), transform.y(outlier), 6.0);
+                    }
+                }
+                series_scene.fill(outlines.clone(), color.scale_alpha(0.3));
+                series_scene.stroke(outlines, color, 1.5);
+                series_scene.stroke(whiskers, color, 1.5);
+                series_scene.stroke(outliers, color, 1.0);
+                series_scene.stroke(medians, Color::BLACK, 2.0);
+            },
+            Series::Violin(violin) => {
+                let mut outlines = Path::new();
+                for polygon in violin.get_polygons() {
+                    let points: Vec<(f32, f32)> = polygon.into_iter().map(|p| transform.point(p)).collect();
+                    outlines.polygon(&points);
+                }
+                let mut medians = Path::new();
+                for (i, median) in violin.get_medians().into_iter().enumerate().filter_map(|(i, m)| Some((i, m?))) {
+                    marker_path(&mut medians, Marker::Point, transform.x(i as f64), transform.y(median), 9.0);
+                }
+                series_scene.fill(outlines.clone(), violin.get_color().scale_alpha(0.5));
+                series_scene.stroke(outlines, *violin.get_color(), 1.0);
+                series_scene.fill(medians, Color::BLACK);
+            },
+            Series::Candlestick(candles) => {
+                let half = candles.get_width()*0.5;
+                for (i, x) in candles.get_positions().into_iter().enumerate() {
+                    let [open, high, low, close] = candles.get_prices(i);
+                    let color = candles.get_color(i);
+                    let mut wick = Path::new();
+                    wick.move_to(transform.x(x), transform.y(high));
+                    wick.line_to(transform.x(x), transform.y(low));
+                    series_scene.stroke(wick, color, 1.0);
+                    // A candle that doesn't move still gets a visible body
+                    let (left, right) = (transform.x(x-half), transform.x(x+half));
+                    let (top, bottom) = (transform.y(open.max(close)), transform.y(open.min(close)));
+                    let mut body = Path::new();
+                    body.polygon(&[(left, top), (right, top), (right, bottom.max(top+1.0)), (left, bottom.max(top+1.0))]);
+                    series_scene.fill(body, color);
+                }
+            },
+            Series::Line(line) => {
+                let marker = line.get_style().marker;
+                // The errors go below the line, a band is a lighter shade of its color
+                let band = line.get_band();
+                if let Some(band) = &band {
+                    let points: Vec<(f32, f32)> = band.iter().map(|&p| transform.point(p)).collect();
+                    let mut path = Path::new();
+                    path.polygon(&points);
+                    series_scene.fill(path, line.get_line_color().scale_alpha(0.25));
+                }
+                let mut errors = Path::new();
+                let cap = line.get_cap_size()*0.5;
+                let bars = band.is_none();
+                for (&(x, y), [x0, x1, y0, y1]) in line.get_data().iter().zip(line.get_error_ranges()) {
+                    let (px, py) = transform.point((x, y));
+                    if line.get_xerr().is_some() {
+                        let (left, right) = (transform.x(x0), transform.x(x1));
+                        errors.move_to(left, py);
+                        errors.line_to(right, py);
+                        for end in [left, right].iter() {
+                            errors.move_to(*end, py-cap);
+                            errors.line_to(*end, py+cap);
+                        }
+                    }
+                    if line.get_yerr().is_some() && bars {
+                        let (top, bottom) = (transform.y(y1), transform.y(y0));
+                        errors.move_to(px, bottom);
+                        errors.line_to(px, top);
+                        for end in [top, bottom].iter() {
+                            errors.move_to(px-cap, *end);
+                            errors.line_to(px+cap, *end);
+                        }
+                    }
+                }
+                if !errors.is_empty() {
+                    series_scene.stroke(errors, line.get_line_color(), 1.0);
+                }
+                if let Some((baseline, stems)) = line.get_stems() {
+                    let mut path = Path::new();
+                    for [bottom, top] in stems {
+                        path.move_to(transform.x(bottom.0), transform.y(bottom.1));
+                        path.line_to(transform.x(top.0), transform.y(top.1));
+                    }
+                    // The baseline goes under all of the stems
+                    let xs = line.get_data().iter().map(|p| p.0).filter(|x| x.is_finite());
+                    let (left, right) = xs.fold((f64::MAX, f64::MIN), |(l, r), x| (l.min(x), r.max(x)));
+                    if left <= right {
+                        path.move_to(transform.x(left), transform.y(baseline));
+                        path.line_to(transform.x(right), transform.y(baseline));
+                    }
+                    series_scene.stroke(path, line.get_line_color(), 1.5);
+                }
+                let mut line_draw = Path::new();
+                let points: Vec<(f32, f32)> = line.get_line_points().into_iter().map(|p| transform.point(p)).collect();
+                line_path(&mut line_draw, &points);
+                let mut faces = Path::new();
+                if let Some(marker) = marker {
+                    for &p in line.get_data().iter() {
+                        let (x, y) = transform.point(p);
+                        marker_path(&mut faces, marker, x, y, line.get_marker_size());
+                    }
+                }
+                series_scene.stroke(line_draw, line.get_line_color(), 2.0);
+                // The markers go on top of the line, open ones only have an edge
+                match marker {
+                    Some(Marker::Point) => series_scene.fill(faces, line.get_face_color()),
+                    Some(marker) if marker.is_open() => series_scene.stroke(faces, line.get_edge_color(), 1.5),
+                    Some(_) => {
+                        series_scene.fill(faces.clone(), line.get_face_color());
+                        series_scene.stroke(faces, line.get_edge_color(), 1.0);
+                    },
+                    None => (),
+                }
+            },
+        }
+        lines.group(&format!("{}{}", series.kind(), n), series_scene);
+    }
+    scene.clip(transform.area(), lines);
+
+    // The colorbar of the first heatmap that has one, next to the plot area
+    let colorbar = plot.get_heatmaps().iter().find(|h| h.has_colorbar()).map(|h| h.get_colorbar(64));
+    if let Some(colors) = colorbar.filter(|c| !c.is_empty()) {
+        let area = transform.area();
+        let left = width-edge+20.0;
+        let step = area.height/colors.len() as f32;
+        for (k, (_, color)) in colors.iter().enumerate() {
+            let bottom = area.y+area.height-k as f32*step;
+            let mut path = Path::new();
+            // Overlap the next step a little so no seams show
+            path.polygon(&[(left, bottom-step-0.5), (left+15.0, bottom-step-0.5), (left+15.0, bottom),
+                           (left, bottom)]);
+            scene.fill(path, *color);
+        }
+        let mut frame = Path::new();
+        frame.polygon(&[(left, area.y), (left+15.0, area.y), (left+15.0, area.y+area.height),
+                        (left, area.y+area.height)]);
+        scene.stroke(frame, Color::BLACK, 1.0);
+        for k in 0..5 {
+            let i = k*(colors.len()-1)/4;
+            let (value, _) = colors[i];
+            let y = area.y+area.height-(i as f32+0.5)*step;
+            texts.push(Text::new(&format!("{:.2}", value), (left+20.0, y))
+                       .align(HAlign::Left, VAlign::Center));
+        }
+    }
+
+    // The volume of the first candlestick series that has one, under the plot area
+    if let Some(candles) = plot.get_candlesticks().iter().find(|c| c.get_volume().is_some()) {
+        let area = transform.area();
+        let volume = candles.get_volume().unwrap();
+        let max = volume.iter().cloned().fold(0.0, f64::max);
+        let (top, bottom) = (height+5.0, full_height-10.0);
+        let half = candles.get_width()*0.5;
+        let mut volume_scene = Scene::new(width, full_height);
+        for (i, x) in candles.get_positions().into_iter().enumerate() {
+            let (left, right) = (transform.x(x-half), transform.x(x+half));
+            let bar_top = bottom-if max > 0.0 {(volume[i]/max) as f32*(bottom-top)} else {0.0};
+            let mut bar = Path::new();
+            bar.polygon(&[(left, bar_top), (right, bar_top), (right, bottom), (left, bottom)]);
+            volume_scene.fill(bar, candles.get_color(i).scale_alpha(0.6));
+        }
+        scene.clip(Rect{x: area.x, y: top, width: area.width, height: bottom-top}, volume_scene);
+        let mut baseline = Path::new();
+        baseline.move_to(area.x, bottom);
+        baseline.line_to(area.x+area.width, bottom);
+        scene.stroke(baseline, Color::BLACK, 1.0);
+        // The largest volume to three significant digits
+        let step = if max > 0.0 {10_f64.powi(max.log10().floor() as i32-2)} else {1.0};
+        let label = format!("Volume (max {:.ndigits$})", (max/step).round()*step, ndigits = decimals(step));
+        texts.push(Text::new(&label, (area.x+4.0, top)).size(12.0).align(HAlign::Left, VAlign::Top));
+    }
+
+    // The title goes above the plot area and the labels next to the arrows of the axes
+    texts.push(Text::new(plot.get_title(), (width*0.5, 2.0)).size(20.0)
+               .align(HAlign::Center, VAlign::Top));
+    texts.push(Text::new(plot.get_xlabel(), (width-edge, y_origin-4.0))
+               .align(HAlign::Right, VAlign::Bottom));
+    texts.push(Text::new(plot.get_ylabel(), (x_origin+6.0, edge))
+               .align(HAlign::Left, VAlign::Top));
+
+    for text in texts {
+        scene.text(text);
+    }
+    scene
+}
+
+// The point at the distance r from the center c in the direction of the angle,
+// counterclockwise from 3 o'clock
+fn polar_point(c: (f32, f32), r: f32, angle: f64) -> (f32, f32) {
+    let p = polar_to_cartesian(r, std::f32::consts::FRAC_PI_2, angle as f32);
+    (c.0+p[0], c.1+p[2])
+}
+
+pub fn pie(pie: &Pie, width: f32, height: f32) -> Scene {
+    let mut scene = Scene::new(width, height);
+    let mut texts = Vec::new();
+    texts.push(Text::new(pie.get_title(), (width*0.5, 2.0)).size(20.0)
+               .align(HAlign::Center, VAlign::Top));
+
+    // Below the title, with room for exploded wedges and the labels around them
+    let top = 30.0;
+    let center = (width*0.5, top+(height-top)*0.5);
+    let max_explode = pie.get_explode().iter().cloned().fold(0.0, f64::max) as f32;
+    let radius = (width.min(height-top)*0.5-EDGE)*0.8/(1.0+max_explode);
+    let at = polar_point;
+
+    let hole = pie.get_hole() as f32;
+    let angles = pie.get_angles();
+    let mut edges = Path::new();
+    for (i, &(start, end)) in angles.iter().enumerate() {
+        if end <= start {
+            continue;
+        }
+        let middle = (start+end)*0.5;
+        let c = at(center, pie.get_explode()[i] as f32*radius, middle);
+        // The arcs are split in steps of at most 2 degrees
+        let steps = ((end-start).to_degrees()/2.0).ceil().max(1.0) as usize;
+        let arc = |r: f32| (0..=steps).map(move |k| {
+            at(c, r, start+(end-start)*k as f64/steps as f64)
+        });
+        let mut points: Vec<(f32, f32)> = arc(radius).collect();
+        if hole > 0.0 {
+            points.extend(arc(hole*radius).collect::<Vec<_>>().into_iter().rev());
+        } else {
+            points.push(c);
+        }
+        let mut wedge = Path::new();
+        wedge.polygon(&points);
+        edges.polygon(&points);
+        let color = pie.get_colors()[i];
+        let mut wedge_scene = Scene::new(width, height);
+        wedge_scene.fill(wedge, color);
+        scene.group(&format!("wedge{}", i), wedge_scene);
+
+        // Percentages go in the middle of the ring, white on dark wedges
+        if pie.has_percentages() {
+            let light = 0.299*color.0+0.587*color.1+0.114*color.2 > 0.5;
+            let mut text = Text::new(&pie.get_percentage(i), at(c, radius*(0.6+hole*0.4), middle))
+                .size(14.0).align(HAlign::Center, VAlign::Center);
+            text.color = if light {Color::BLACK} else {Color(1.0, 1.0, 1.0, 1.0)};
+            texts.push(text);
+        }
+        if let Some(label) = pie.get_label(i) {
+            let cos = middle.cos();
+            let align = if cos > 0.1 {HAlign::Left} else if cos < -0.1 {HAlign::Right} else {HAlign::Center};
+            texts.push(Text::new(label, at(c, radius*1.1, middle)).align(align, VAlign::Center));
+        }
+    }
+    // White edges keep wedges of similar colors apart
+    if angles.len() > 1 {
+        scene.stroke(edges, Color(1.0, 1.0, 1.0, 1.0), 1.5);
+    }
+
+    for text in texts {
+        scene.text(text);
+    }
+    scene
+}
+
+pub fn polar(plot: &PolarPlot, width: f32, height: f32) -> Scene {
+    let mut scene = Scene::new(width, height);
+    let mut texts = Vec::new();
+    texts.push(Text::new(plot.get_title(), (width*0.5, 2.0)).size(20.0)
+               .align(HAlign::Center, VAlign::Top));
+
+    // Below the title, with room for the angles around the outer circle
+    let top = 30.0;
+    let center = (width*0.5, top+(height-top)*0.5);
+    let radius = width.min(height-top)*0.5-EDGE-10.0;
+    let rlim = plot.get_rlim();
+    // Points with radii outside the limits aren't drawn
+    let to_window = |(theta, r): (f64, f64)| {
+        let r = (r-rlim[0])/(rlim[1]-rlim[0]);
+        if (0.0..=1.0).contains(&r) && theta.is_finite() {Some(polar_point(center, r as f32*radius, theta))} else {None}
+    };
+
+    let mut grid = Path::new();
+    let rings = plot.get_rings();
+    let spacing = (rlim[1]-rlim[0])/rings as f64;
+    let nbr_of_digits = decimals(spacing);
+    for k in 1..=rings {
+        let r = radius*k as f32/rings as f32;
+        grid.circle(center.0, center.1, r);
+        // The values of the rings go between the first two spokes
+        let value = format!("{:.ndigits$}", rlim[0]+k as f64*spacing, ndigits = nbr_of_digits);
+        texts.push(Text::new(&value, polar_point(center, r, 15_f64.to_radians())).size(12.0)
+                   .align(HAlign::Left, VAlign::Bottom));
+    }
+    for (angle, label) in plot.get_angle_labels() {
+        let (x, y) = polar_point(center, radius, angle);
+        grid.move_to(center.0, center.1);
+        grid.line_to(x, y);
+        let (cos, sin) = (angle.cos(), angle.sin());
+        let horizontal = if cos > 0.1 {HAlign::Left} else if cos < -0.1 {HAlign::Right} else {HAlign::Center};
+        let vertical = if sin > 0.1 {VAlign::Bottom} else if sin < -0.1 {VAlign::Top} else {VAlign::Center};
+        texts.push(Text::new(&label, polar_point(center, radius+6.0, angle)).align(horizontal, vertical));
+    }
+    scene.stroke(grid, Color(0.0, 0.0, 0.0, 0.25), 1.0);
+    let mut frame = Path::new();
+    frame.circle(center.0, center.1, radius);
+    scene.stroke(frame, Color::BLACK, 1.5);
+
+    for (n, line) in plot.get_lines().iter().enumerate() {
+        let mut line_scene = Scene::new(width, height);
+        let mut path = Path::new();
+        // The line breaks where it leaves the limits and starts again where it comes back
+        for part in line.get_line_points().into_iter().map(to_window).collect::<Vec<_>>().split(|p| p.is_none()) {
+            let points: Vec<(f32, f32)> = part.iter().flatten().copied().collect();
+            line_path(&mut path, &points);
+        }
+        line_scene.stroke(path, line.get_line_color(), 2.0);
+        if let Some(marker) = line.get_style().marker {
+            let mut faces = Path::new();
+            for (x, y) in line.get_data().iter().filter_map(|&p| to_window(p)) {
+                marker_path(&mut faces, marker, x, y, line.get_marker_size());
+            }
+            // Like in plot2d, open markers only have an edge
+            match marker {
+                Marker::Point => line_scene.fill(faces, line.get_face_color()),
+                marker if marker.is_open() => line_scene.stroke(faces, line.get_edge_color(), 1.5),
+                _ => {
+                    line_scene.fill(faces.clone(), line.get_face_color());
+                    line_scene.stroke(faces, line.get_edge_color(), 1.0);
+                },
+            }
+        }
+        scene.group(&format!("line{}", n), line_scene);
+    }
+
+    for text in texts {
+        scene.text(text);
+    }
+    scene
+}
+
+pub fn plot3d(plot: &Plot3D, width: f32, height: f32, angles: (f32, f32)) -> Scene {
+    let mut scene = Scene::new(width, height);
+    let mut texts = Vec::new();
+
+    // Draw the title
+    texts.push(Text::new(plot.get_title(), (width*0.5, height*0.05)).size(30.0)
+               .align(HAlign::Center, VAlign::Top));
+
+    // Get the axis limits
+    let xlims = plot.get_axes().get_axes().get_xaxes();
+    let ylims = plot.get_axes().get_axes().get_yaxes();
+    let zlims = plot.get_axes().get_axes().get_zaxes();
+
+    // Get the largest absolue value to
+    // make sure the grid can fit all points no matter the rotation
+    let max_val: f32 = (xlims[1].max(ylims[1])).max(zlims[1]);
+    let min_val: f32 = (xlims[0]).min(ylims[0]).min(zlims[0]);
+    let max_val = max_val.max(min_val.abs());
+
+    // Generate the camera. Set the camera to look at the origin
+    let camera_view = create_camera(&camera_position(angles), &Vector3::<f32>::zeros());
+
+    // Project a point and map it to the window, leaving some extra space so
+    // everything will fit nicely
+    let to_window = |p: [f32;3]| {
+        let p = project(&camera_view, &p.into());
+        (width*0.1+(p[0]+max_val*1.5)/(max_val*3.0)*width*0.8,
+         height*0.9-(p[1]+max_val*1.5)/(max_val*3.0)*height*0.8)
+    };
+
+    let axes_3d = plot.get_axes().get_axes();
+    let x_spacing = axes_3d.get_xspacing();
+    let y_spacing = axes_3d.get_yspacing();
+    let z_spacing = axes_3d.get_zspacing();
+    let nbr_of_z_values = ((zlims[1]-zlims[0])/(z_spacing)) as usize;
+    let nbr_of_x_values = ((xlims[1]-xlims[0])/(x_spacing)) as usize;
+    let nbr_of_y_values = ((ylims[1]-ylims[0])/(y_spacing)) as usize;
+    let spacing = (x_spacing.max(y_spacing)).max(z_spacing);
+
+    // Generate an array of values for the axes
+    let x_vals_lin = Linspace::linspace_f32(xlims[0], xlims[1], nbr_of_x_values);
+    let y_vals_lin = Linspace::linspace_f32(ylims[0], ylims[1], nbr_of_y_values);
+    let z_vals_lin = Linspace::linspace_f32(zlims[0], zlims[1], nbr_of_z_values);
+
+    // Set locations for where the axes are drawn.
+    let mut ystart = xlims[0];
+    let mut zstart = ylims[1];
+    let zxstart = xlims[0];
+    let zxend = xlims[1];
+    let mut xstart = ylims[0];
+    let mut sign = 1.0;
+    // Change location of axes depending on angle to make sure they are always visible
+    if angles.1 < -180.0 {
+        ystart = xlims[0];
+        zstart = ylims[0];
+        xstart = ylims[1];
+        sign = -1.0;
+    }
+
+    // Determine number of decimals to present on axes
+    let nbr_of_x_digits = decimals(x_spacing as f64);
+    let nbr_of_y_digits = decimals(y_spacing as f64);
+    let nbr_of_z_digits = decimals(z_spacing as f64);
+
+    let mut dash_drawer = Path::new();
+    let mut grid_rectangle = Path::new();
+    let add_dash = |path: &mut Path, a: [f32;3], b: [f32;3]| {
+        let (start_x, start_y) = to_window(a);
+        let (end_x, end_y) = to_window(b);
+        path.move_to(start_x, start_y);
+        path.line_to(end_x, end_y);
+    };
+    let add_rectangle = |path: &mut Path, corners: [[f32;3];4]| {
+        let points: Vec<(f32, f32)> = corners.iter().map(|&c| to_window(c)).collect();
+        path.polygon(&points);
+    };
+
+    // Draw the x axes dashes and texts
+    for &val in x_vals_lin.iter() {
+        add_dash(&mut dash_drawer, [val, zlims[0], -xstart+sign*spacing/4.0],
+                 [val, zlims[0], -xstart-sign*spacing/4.0]);
+        let position = to_window([val, zlims[0], -xstart+sign*spacing/2.0]);
+        texts.push(Text::new(&format!("{:.ndigits$}", val, ndigits = nbr_of_x_digits), position)
+                   .align(HAlign::Center, VAlign::Top));
+    }
+    // Draw the z-axes dashes and texts
+    for &val in z_vals_lin.iter() {
+        add_dash(&mut dash_drawer, [zxstart, val, -zstart-sign*spacing*0.25],
+                 [zxstart, val, -zstart+sign*spacing*0.25]);
+        let position = to_window([zxstart, val, -zstart-sign*spacing*0.5]);
+        texts.push(Text::new(&format!("{:.ndigits$}", val, ndigits = nbr_of_z_digits), position)
+                   .align(HAlign::Left, VAlign::Top));
+    }
+    // Draw the y axes dashes and texts
+    for &val in y_vals_lin.iter() {
+        add_dash(&mut dash_drawer, [ystart-spacing/4.0, zlims[0], -val],
+                 [ystart+spacing/4.0, zlims[0], -val]);
+        let position = to_window([ystart-spacing*0.5, zlims[0], -val]);
+        texts.push(Text::new(&format!("{:.ndigits$}", val, ndigits = nbr_of_y_digits), position)
+                   .align(HAlign::Left, VAlign::Top));
+    }
+
+    // Draw the xy, xz and zy grids as rectangles
+    for i in 0..x_vals_lin.len().saturating_sub(1) {
+        for j in 0..y_vals_lin.len().saturating_sub(1) {
+            add_rectangle(&mut grid_rectangle, [[x_vals_lin[i], zlims[0], -y_vals_lin[j]],
+                                                [x_vals_lin[i], zlims[0], -y_vals_lin[j+1]],
+                                                [x_vals_lin[i+1], zlims[0], -y_vals_lin[j+1]],
+                                                [x_vals_lin[i+1], zlims[0], -y_vals_lin[j]]]);
+        }
+        for j in 0..z_vals_lin.len().saturating_sub(1) {
+            add_rectangle(&mut grid_rectangle, [[x_vals_lin[i], z_vals_lin[j], -zstart],
+                                                [x_vals_lin[i], z_vals_lin[j+1], -zstart],
+                                                [x_vals_lin[i+1], z_vals_lin[j+1], -zstart],
+                                                [x_vals_lin[i+1], z_vals_lin[j], -zstart]]);
+        }
+    }
+    for i in 0..z_vals_lin.len().saturating_sub(1) {
+        for j in 0..y_vals_lin.len().saturating_sub(1) {
+            add_rectangle(&mut grid_rectangle, [[zxend, z_vals_lin[i], -y_vals_lin[j]],
+                                                [zxend, z_vals_lin[i], -y_vals_lin[j+1]],
+                                                [zxend, z_vals_lin[i+1], -y_vals_lin[j+1]],
+                                                [zxend, z_vals_lin[i+1], -y_vals_lin[j]]]);
+        }
+    }
+
+    // Add the rectangles and the dashes
+    scene.fill(grid_rectangle.clone(), Color(0.99, 0.99, 0.99, 1.0));
+    scene.stroke(grid_rectangle, Color(0.4, 0.4, 0.4, 1.0), 2.0);
+    scene.stroke(dash_drawer, Color::BLACK, 2.0);
+
+    // Draw the axes lines and their labels. Spacing for the labels are chosen after observations
+    let x_label_spacing = (xlims[1]-xlims[0])/30.0;
+    let y_label_spacing = (ylims[1]-ylims[0])/10.0;
+    let z_label_spacing = (zlims[1]-zlims[0])/8.0;
+    let all_axes = [([xlims[0], zlims[0], -xstart], [xlims[1], zlims[0], -xstart]),
+                    ([ystart, zlims[0], -ylims[0]], [ystart, zlims[0], -ylims[1]]),
+                    ([zxstart, zlims[0], -zstart], [zxstart, zlims[1], -zstart])];
+    let labels_pos = [[xlims[1]+x_label_spacing, zlims[0], -xstart],
+                      [ystart, zlims[0], -ylims[1]-y_label_spacing],
+                      [zxstart, zlims[1]+z_label_spacing, -zstart]];
+    let labels = [plot.get_xlabel(), plot.get_ylabel(), plot.get_zlabel()];
+    let mut axes_drawer = Path::new();
+    for i in 0..3 {
+        add_dash(&mut axes_drawer, all_axes[i].0, all_axes[i].1);
+        texts.push(Text::new(labels[i], to_window(labels_pos[i])).size(20.0)
+                   .align(HAlign::Center, VAlign::Center));
+    }
+    scene.stroke(axes_drawer, Color::BLACK, 2.0);
+
+    // Draw the surface plot. Loops through the rows(y-values) and columns(x-values)
+    // and creates a rectangle at each z-value with the chosen color.
+    if let Some(s) = plot.get_surface() {
+        let rows = s.z_data.nrows();
+        let cols = s.z_data.ncols();
+        let mut rectangles = Vec::new();
+        for row in 0..rows.saturating_sub(1) {
+            for col in 0..cols.saturating_sub(1) {
+                // Get the 4 points of the rectangle
+                let x1 = s.x_data[(0, col)];
+                let y1 = s.y_data[(row, 0)];
+                let x2 = s.x_data[(0, col+1)];
+                let y2 = s.y_data[(row+1, 0)];
+                let corners = [[x1, s.z_data[(row, col)], -y1],
+                               [x2, s.z_data[(row, col+1)], -y1],
+                               [x2, s.z_data[(row+1, col+1)], -y2],
+                               [x1, s.z_data[(row+1, col)], -y2]];
+                let mut rectangle = Path::new();
+                add_rectangle(&mut rectangle, corners);
+                // Choose color depeneding on plot settings
+                let color = if let Some(c) = &s.colormap {
+                    let (r, g, b) = c.0[rows*col+row];
+                    Color(r, g, b, 1.0)
+                } else if let Some(c) = s.get_color() {
+                    *c
+                } else {
+                    Color::BLUE
+                };
+                let depth = corners.iter().map(|&c| depth(&camera_view, &c.into())).sum::<f32>()/4.0;
+                rectangles.push((depth, rectangle, color));
+            }
+        }
+        // Draw the rectangles furthest away from the camera first so the closer
+        // ones cover them
+        rectangles.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        for (_, rectangle, color) in rectangles {
+            scene.fill(rectangle.clone(), color);
+            scene.stroke(rectangle, Color::BLACK, 2.0);
+        }
+    }
+
+    for text in texts {
+        scene.text(text);
+    }
+    scene
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals_follow_spacing() {
+        assert_eq!(decimals(2.0), 0);
+        assert_eq!(decimals(0.5), 1);
+        assert_eq!(decimals(0.25), 2);
+    }
+
+    #[test]
+    fn clipping_keeps_what_is_inside_the_rect() {
+        let rect = Rect{x: 0.0, y: 0.0, width: 10.0, height: 10.0};
+        let mut line = Path::new();
+        line.move_to(-10.0, 5.0);
+        line.line_to(20.0, 5.0);
+        line.line_to(20.0, 20.0);
+        assert_eq!(rect.clip_stroke(&line).segments, [Segment::MoveTo(0.0, 5.0), Segment::LineTo(10.0, 5.0)]);
+
+        let mut square = Path::new();
+        square.polygon(&[(5.0, 5.0), (15.0, 5.0), (15.0, 15.0), (5.0, 15.0)]);
+        let corners: Vec<(f32, f32)> = rect.clip_fill(&square).flatten().remove(0).0;
+        assert_eq!(corners, [(5.0, 10.0), (5.0, 5.0), (10.0, 5.0), (10.0, 10.0)]);
+    }
+
+    #[test]
+    fn markers_are_drawn_on_the_points() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]).marker(Marker::Square)
+                                .face_color(Color::RED).alpha(0.5));
+        let scene = plot.scene(200.0, 100.0);
+        let group = match &scene.primitives.iter().find(|p| matches!(p, Primitive::Clip{..})).unwrap() {
+            Primitive::Clip{primitives, ..} => primitives[0].clone(),
+            _ => unreachable!(),
+        };
+        match group {
+            Primitive::Group{primitives, ..} => {
+                assert_eq!(primitives.len(), 3);
+                match &primitives[1] {
+                    Primitive::Fill{path, color} => {
+                        // Squares 6 pixels wide on the corners of the plot area,
+                        // which leaves an edge of 30 pixels
+                        let mut squares = Path::new();
+                        squares.polygon(&[(27.0, 67.0), (33.0, 67.0), (33.0, 73.0), (27.0, 73.0)]);
+                        squares.polygon(&[(167.0, 27.0), (173.0, 27.0), (173.0, 33.0), (167.0, 33.0)]);
+                        assert_eq!(*path, squares);
+                        assert_eq!(*color, Color(1.0, 0.0, 0.0, 0.5));
+                    },
+                    _ => panic!("Expected the markers to be filled"),
+                }
+            },
+            _ => panic!("Expected the line to be grouped"),
+        }
+    }
+
+    #[test]
+    fn series_are_drawn_in_the_order_they_were_added() {
+        let mut plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]));
+        plot.add_bar(Bar2D::new(&[0.5], &[2.0]));
+        plot.add_line(Line2D::new(&[0.0, 1.0], &[1.0, 0.0]));
+        let scene = plot.scene(200.0, 100.0);
+        let names: Vec<&str> = scene.primitives.iter().find_map(|p| match p {
+            Primitive::Clip{primitives, ..} => Some(primitives.iter().filter_map(|p| match p {
+                Primitive::Group{name, ..} => Some(name.as_str()),
+                _ => None,
+            }).collect()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(names, ["line0", "bar0", "line1"]);
+    }
+
+    #[test]
+    fn colorbars_make_room_next_to_the_plot() {
+        let data = nalgebra::DMatrix::from_row_slice(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let plot = Plot2D::heatmap(Heatmap2D::new(data).colorbar());
+        assert_eq!(Transform2D::new(&plot, 200.0, 100.0).area().width, 200.0-2.0*EDGE-colorbar_width(&plot));
+        // Cells without a number are left out of the cells and the colorbar
+        let data = nalgebra::DMatrix::from_row_slice(1, 2, &[f32::NAN, 1.0]);
+        plot2d(&Plot2D::heatmap(Heatmap2D::new(data).colorbar()), 200.0, 100.0);
+    }
+
+    #[test]
+    fn pie_labels_follow_the_wedges() {
+        let pie = Pie::new(&[1.0, 3.0]).start_angle(0.0).labels(&["a", "b"]);
+        let scene = pie.scene(400.0, 430.0);
+        let texts: Vec<&str> = scene.primitives.iter().filter_map(|p| match p {
+            Primitive::Text(t) => Some(t.content.as_str()),
+            _ => None,
+        }).collect();
+        assert_eq!(texts, ["Plot", "25.0%", "a", "75.0%", "b"]);
+    }
+
+    #[test]
+    fn polar_lines_are_placed_between_the_rings() {
+        let theta: Vec<f64> = (0..=8).map(|i| i as f64*std::f64::consts::PI/4.0).collect();
+        let plot = PolarPlot::plot(Line2D::new(&theta, &[1.0, 2.0, 3.0, 2.0, 1.0, 2.0, 3.0, 2.0, 1.0]));
+        // The point at 90 degrees and r = 3 is on top of the outer circle
+        let scene = plot.rlim(&[0.0, 4.0]).scene(400.0, 430.0);
+        let line = scene.primitives.iter().find_map(|p| match p {
+            Primitive::Group{primitives, ..} => Some(primitives[0].clone()),
+            _ => None,
+        }).unwrap();
+        match line {
+            Primitive::Stroke{path, ..} => {
+                match path.segments[2] {
+                    Segment::LineTo(x, y) => {
+                        assert!((x-200.0).abs() < 1e-3);
+                        assert!((y-(230.0-0.75*160.0)).abs() < 1e-3);
+                    },
+                    _ => panic!("Expected a line"),
+                }
+            },
+            _ => panic!("Expected a stroke"),
+        }
+
+        // The line breaks at the radius outside the limits
+        let plot = PolarPlot::plot(Line2D::new(&[0.0, 1.0, 2.0, 3.0], &[1.0, 5.0, 1.0, 1.0])).rlim(&[0.0, 2.0]);
+        let scene = plot.scene(400.0, 430.0);
+        let line = scene.primitives.iter().find_map(|p| match p {
+            Primitive::Group{primitives, ..} => Some(primitives[0].clone()),
+            _ => None,
+        }).unwrap();
+        match line {
+            Primitive::Stroke{path, ..} => {
+                let kinds: Vec<bool> = path.segments.iter().map(|s| matches!(s, Segment::MoveTo(..))).collect();
+                assert_eq!(kinds, [true, true, false]);
+            },
+            _ => panic!("Expected a stroke"),
+        }
+
+        // Filled markers get their edge like in plot2d
+        let plot = PolarPlot::plot(Line2D::new(&[0.0, 1.0], &[1.0, 2.0]).marker(Marker::Circle)
+                                   .edge_color(Color::RED));
+        let edges = plot.scene(400.0, 430.0).primitives.iter().find_map(|p| match p {
+            Primitive::Group{primitives, ..} => Some(primitives[2].clone()),
+            _ => None,
+        }).unwrap();
+        assert!(matches!(edges, Primitive::Stroke{color: Color::RED, ..}));
+    }
+
+    #[test]
+    fn candles_make_room_for_volume() {
+        // Thursday 2024-02-29, Friday and the Monday after them
+        let days = [1_709_164_800, 1_709_251_200, 1_709_510_400];
+        let candles = |volume: &[f64]| Candlestick::new(&days, &[10.0, 12.0, 11.0], &[13.0, 12.5, 11.5],
+                                                        &[9.0, 8.0, 10.0], &[12.0, 9.0, 11.0]).volume(volume);
+        let plot = Plot2D::candlestick(candles(&[100.0, 300.0, 200.0]));
+        assert_eq!(Transform2D::new(&plot, 200.0, 400.0).area().height, 300.0-2.0*EDGE);
+
+        // The largest volume is rounded, every candle is named once
+        let plot = Plot2D::candlestick(candles(&[12_345_678.9, 300.0, 200.0]));
+        let texts: Vec<String> = plot.scene(200.0, 400.0).primitives.iter().filter_map(|p| match p {
+            Primitive::Text(text) => Some(text.content.clone()),
+            _ => None,
+        }).collect();
+        assert!(texts.contains(&"Volume (max 12300000)".to_string()));
+        assert_eq!(texts.iter().filter(|t| t.as_str() == "Mar 1").count(), 1);
+    }
+
+    #[test]
+    fn lines_are_clipped_to_the_plot_area() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]));
+        let scene = plot.scene(200.0, 100.0);
+        let clip = scene.primitives.iter().find_map(|p| match p {
+            Primitive::Clip{rect, primitives} => Some((rect, primitives)),
+            _ => None,
+        }).unwrap();
+        assert_eq!(*clip.0, Rect{x: EDGE, y: EDGE, width: 200.0-2.0*EDGE, height: 100.0-2.0*EDGE});
+        let line = match &clip.1[0] {
+            Primitive::Group{name, primitives} if name == "line0" => &primitives[0],
+            _ => panic!("Expected the line to be grouped"),
+        };
+        match line {
+            Primitive::Stroke{path, ..} => {
+                assert_eq!(path.segments, vec![Segment::MoveTo(EDGE, 100.0-EDGE),
+                                               Segment::LineTo(200.0-EDGE, EDGE)]);
+            },
+            _ => panic!("Expected the line to be stroked"),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::font;
+use crate::plot::Color;
+use crate::scene::{self, Scene, Primitive, Segment, HAlign, VAlign};
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+// Writes scenes as standalone SVG documents. Text uses the same font as the
+// raster output, with a generic fallback for viewers that don't have it.
+
+fn color(color: &Color) -> String {
+    format!("rgb({},{},{})", (color.0*255.0).round() as u8, (color.1*255.0).round() as u8,
+            (color.2*255.0).round() as u8)
+}
+
+fn opacity(attribute: &str, color: &Color) -> String {
+    if color.3 < 1.0 {
+        format!(" {}=\"{}\"", attribute, color.3)
+    } else {
+        String::new()
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn path_data(path: &scene::Path) -> String {
+    let mut data = String::new();
+    for segment in path.segments.iter() {
+        match *segment {
+            Segment::MoveTo(x, y) => write!(data, "M{:.2} {:.2}", x, y),
+            Segment::LineTo(x, y) => write!(data, "L{:.2} {:.2}", x, y),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => {
+                write!(data, "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2}", x1, y1, x2, y2, x, y)
+            },
+            Segment::Close => write!(data, "Z"),
+        }.unwrap();
+    }
+    data
+}
+
+pub(crate) fn text_element(text: &scene::Text) -> String {
+    let anchor = match text.horizontal_alignment {
+        HAlign::Left => "start",
+        HAlign::Center => "middle",
+        HAlign::Right => "end",
+    };
+    // Place the baseline the same way as the other backends
+    let (ascent, descent) = font::vertical_metrics(text.size);
+    let y = match text.vertical_alignment {
+        VAlign::Top => text.position.1+ascent,
+        VAlign::Center => text.position.1+(ascent+descent)*0.5,
+        VAlign::Bottom => text.position.1+descent,
+    };
+    format!("<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{}\" text-anchor=\"{}\" fill=\"{}\"{}>{}</text>\n",
+            text.position.0, y, font::em_size(text.size), anchor, color(&text.color),
+            opacity("fill-opacity", &text.color), escape(&text.content))
+}
+
+fn write_primitives(svg: &mut String, primitives: &[Primitive], clip_count: &mut usize) {
+    for primitive in primitives {
+        match primitive {
+            Primitive::Stroke{path, color: c, width} => {
+                writeln!(svg, "<path d=\"{}\" fill=\"none\" stroke=\"{}\"{} stroke-width=\"{}\" \
+                         stroke-linecap=\"butt\" stroke-linejoin=\"miter\"/>",
+                         path_data(path), color(c), opacity("stroke-opacity", c), width).unwrap();
+            },
+            Primitive::Fill{path, color: c} => {
+                writeln!(svg, "<path d=\"{}\" fill=\"{}\"{}/>", path_data(path), color(c),
+                         opacity("fill-opacity", c)).unwrap();
+            },
+            Primitive::Text(text) => svg.push_str(&text_element(text)),
+            Primitive::Clip{rect, primitives} => {
+                *clip_count += 1;
+                let id = format!("clip{}", clip_count);
+                writeln!(svg, "<clipPath id=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/></clipPath>",
+                         id, rect.x, rect.y, rect.width, rect.height).unwrap();
+                writeln!(svg, "<g clip-path=\"url(#{})\">", id).unwrap();
+                write_primitives(svg, primitives, clip_count);
+                svg.push_str("</g>\n");
+            },
+            Primitive::Group{name, primitives} => {
+                writeln!(svg, "<g id=\"{}\">", escape(name)).unwrap();
+                write_primitives(svg, primitives, clip_count);
+                svg.push_str("</g>\n");
+            },
+        }
+    }
+}
+
+// The elements of the scene without the surrounding svg tag.
+pub(crate) fn body(scene: &Scene) -> String {
+    let mut svg = String::new();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"{}/>", color(&scene.background),
+             opacity("fill-opacity", &scene.background)).unwrap();
+    write_primitives(&mut svg, &scene.primitives, &mut 0);
+    svg
+}
+
+pub fn render(scene: &Scene) -> String {
+    let mut svg = String::new();
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"DejaVu Sans, sans-serif\">",
+             w = scene.width, h = scene.height).unwrap();
+    svg.push_str(&body(scene));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+pub fn save<P: AsRef<std::path::Path>>(scene: &Scene, path: P) -> io::Result<()> {
+    fs::write(path, render(scene))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot::*;
+
+    #[test]
+    fn svg_contains_lines_and_escaped_labels() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]).color(Color::RED))
+            .title("a < b");
+        let svg = plot.to_svg_string(200, 100);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("stroke=\"rgb(255,0,0)\""));
+        assert!(svg.contains(">a &lt; b</text>"));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::plot::{Color, HistType, Plot2D, Series};
+use crate::scene::decimals;
+
+use std::fmt::Write;
+
+// Draws 2D plots as text for terminals without a display. Every character of
+// the plot area is a braille pattern with 2x4 dots, so the lines get a higher
+// resolution than the character grid.
+
+// Tick values are written like in the window, but with at most two decimals to
+// save columns
+fn tick_label(value: f64, digits: usize) -> String {
+    format!("{:.ndigits$}", value, ndigits = digits.min(2))
+}
+
+// ANSI escape for a 24 bit foreground color. Black keeps the default color of
+// the terminal so the lines show up on dark backgrounds too.
+fn ansi(color: &Color) -> Option<String> {
+    if color.0 == 0.0 && color.1 == 0.0 && color.2 == 0.0 {
+        return None;
+    }
+    Some(format!("\x1b[38;2;{};{};{}m", (color.0*255.0) as u8, (color.1*255.0) as u8,
+                 (color.2*255.0) as u8))
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    // Braille dots and the color of every character
+    cells: Vec<(u8, Option<Color>)>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self{width, height, cells: vec![(0, None); width*height]}
+    }
+
+    // Sets a dot, with (0, 0) in the top left corner of the dot grid.
+    fn dot(&mut self, x: i64, y: i64, color: &Color) {
+        if x < 0 || y < 0 || x >= 2*self.width as i64 || y >= 4*self.height as i64 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let bit = match (x%2, y%4) {
+            (0, 3) => 0x40,
+            (1, 3) => 0x80,
+            (0, row) => 1 << row,
+            (_, row) => 1 << (row+3),
+        };
+        let cell = &mut self.cells[(y/4)*self.width+x/2];
+        cell.0 |= bit;
+        cell.1 = Some(*color);
+    }
+
+    // Sets the dot closest to a point in dot coordinates, if it has any.
+    fn point(&mut self, (x, y): (f64, f64), color: &Color) {
+        if x.is_finite() && y.is_finite() {
+            self.dot(x.round() as i64, y.round() as i64, color);
+        }
+    }
+
+    fn line(&mut self, start: (f64, f64), end: (f64, f64), color: &Color) {
+        // Only the part on the grid is drawn, points far away or at infinity
+        // would make the loop below endless
+        let (start, end) = match self.clip(start, end) {
+            Some(segment) => segment,
+            None => return,
+        };
+        let end = (end.0.round() as i64, end.1.round() as i64);
+        // Bresenham's line algorithm
+        let (mut x, mut y) = (start.0.round() as i64, start.1.round() as i64);
+        let dx = (end.0-x).abs();
+        let dy = -(end.1-y).abs();
+        let sx = if x < end.0 {1} else {-1};
+        let sy = if y < end.1 {1} else {-1};
+        let mut error = dx+dy;
+        loop {
+            self.dot(x, y, color);
+            if (x, y) == end {
+                break;
+            }
+            let e2 = 2*error;
+            if e2 >= dy {
+                error += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // The part of a segment within half a dot of the grid with the Liang-Barsky
+    // algorithm, none for segments outside of it or with a non-finite end.
+    fn clip(&self, start: (f64, f64), end: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+        if !(start.0.is_finite() && start.1.is_finite() && end.0.is_finite() && end.1.is_finite()) {
+            return None;
+        }
+        let (x_max, y_max) = ((2*self.width) as f64-0.5, (4*self.height) as f64-0.5);
+        // Half of the distances, the whole ones overflow between the largest floats
+        let (dx, dy) = (end.0*0.5-start.0*0.5, end.1*0.5-start.1*0.5);
+        let bounds = [(-dx, start.0+0.5), (dx, x_max-start.0), (-dy, start.1+0.5), (dy, y_max-start.1)];
+        let (mut t0, mut t1) = ((0.0f64, None), (1.0f64, None));
+        for (side, &(p, q)) in bounds.iter().enumerate() {
+            let t = q*0.5/p;
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 && t > t0.0 {
+                t0 = (t, Some(side));
+            } else if p > 0.0 && t < t1.0 {
+                t1 = (t, Some(side));
+            }
+        }
+        if t0.0 > t1.0 {
+            return None;
+        }
+        // Far away ends lose the precision of their position, so the side the
+        // segment was cut at is set exactly and the rest kept on the grid
+        let point = |(t, side): (f64, Option<usize>)| {
+            let mut point = (start.0+2.0*(t*dx), start.1+2.0*(t*dy));
+            match side {
+                Some(0) => point.0 = -0.5,
+                Some(1) => point.0 = x_max,
+                Some(2) => point.1 = -0.5,
+                Some(3) => point.1 = y_max,
+                _ => (),
+            }
+            (point.0.max(-0.5).min(x_max), point.1.max(-0.5).min(y_max))
+        };
+        Some((point(t0), point(t1)))
+    }
+
+    // Sets every dot between two corners that is on the grid.
+    fn rect(&mut self, corner: (f64, f64), opposite: (f64, f64), color: &Color) {
+        if !(corner.0.is_finite() && corner.1.is_finite() && opposite.0.is_finite() && opposite.1.is_finite()) {
+            return;
+        }
+        let columns = |a: f64, b: f64, size: usize| {
+            let (a, b) = (a.round().max(-1.0), b.round().min(size as f64));
+            (a as i64, b as i64)
+        };
+        let (left, right) = columns(corner.0.min(opposite.0), corner.0.max(opposite.0), 2*self.width);
+        let (top, bottom) = columns(corner.1.min(opposite.1), corner.1.max(opposite.1), 4*self.height);
+        for y in top..=bottom {
+            for x in left..=right {
+                self.dot(x, y, color);
+            }
+        }
+    }
+
+    fn row(&self, row: usize, out: &mut String) {
+        let mut current: Option<String> = None;
+        for cell in &self.cells[row*self.width..(row+1)*self.width] {
+            let escape = if cell.0 != 0 { cell.1.as_ref().and_then(ansi) } else { None };
+            // Only switch colors between characters that differ
+            if escape != current {
+                if current.is_some() {
+                    out.push_str("\x1b[0m");
+                }
+                if let Some(escape) = &escape {
+                    out.push_str(escape);
+                }
+                current = escape;
+            }
+            out.push(std::char::from_u32(0x2800+cell.0 as u32).unwrap());
+        }
+        if current.is_some() {
+            out.push_str("\x1b[0m");
+        }
+    }
+}
+
+// Lays out the plot in `cols` x `rows` characters. The title and the y label
+// are on the first row, the x axis and its values on the last two.
+pub fn render(plot: &Plot2D, cols: usize, rows: usize) -> String {
+    let xlims = plot.get_axes().get_axes().get_xaxes();
+    let ylims = plot.get_axes().get_axes().get_yaxes();
+    let nvalues = plot.get_axes().get_axes().get_nvalues().max(1);
+    let height = rows.saturating_sub(3).max(1);
+
+    // Fewer ticks than in the window, the characters are much larger than pixels
+    let y_ticks = nvalues.min(height/2).max(1);
+    let y_spacing = (ylims[1]-ylims[0])/y_ticks as f64;
+    let y_digits = decimals(y_spacing);
+    let y_labels: Vec<String> = (0..=y_ticks)
+        .map(|i| tick_label(ylims[0]+i as f64*y_spacing, y_digits))
+        .collect();
+    let margin = y_labels.iter().map(|l| l.chars().count()).max().unwrap_or(0)+1;
+    // The x label goes to the right of the x axis
+    let xlabel = plot.get_xlabel();
+    let width = cols.saturating_sub(margin+2+xlabel.chars().count()).max(1);
+    let x_ticks = nvalues.min(width/10).max(1);
+    let x_spacing = (xlims[1]-xlims[0])/x_ticks as f64;
+    let x_digits = decimals(x_spacing);
+
+    // Dot coordinates of a data point
+    let to_dots = |(x, y): (f64, f64)| {
+        let dx = (x-xlims[0])/(xlims[1]-xlims[0])*(2*width-1) as f64;
+        let dy = (ylims[1]-y)/(ylims[1]-ylims[0])*(4*height-1) as f64;
+        (dx, dy)
+    };
+    let mut canvas = Canvas::new(width, height);
+    let bar_rects = plot.get_bar_rects();
+    let hist_rects = plot.get_hist_rects();
+    let hist_outlines = plot.get_hist_outlines();
+    // Boxes and violins only by their outlines
+    let outline = |canvas: &mut Canvas, points: &[(f64, f64)], color: &Color| {
+        for i in 0..points.len() {
+            canvas.line(to_dots(points[i]), to_dots(points[(i+1)%points.len()]), color);
+        }
+    };
+    // In the order the series were added like in the window, bars and
+    // histograms need the index within their kind to find their rectangles
+    let (mut nbars, mut nhists) = (0, 0);
+    for series in plot.get_series() {
+        match series {
+            // Every dot of a heatmap cell gets its color, a character shows the last one
+            Series::Heatmap(heatmap) => {
+                for ([x0, x1, y0, y1], color) in heatmap.get_cells() {
+                    canvas.rect(to_dots((x0, y1)), to_dots((x1, y0)), &color);
+                }
+            },
+            // Contours only by their lines, filled ones too since they border the bands
+            Series::Contour(contour) => {
+                for (_, lines) in contour.get_lines() {
+                    for line in lines {
+                        for pair in line.windows(2) {
+                            canvas.line(to_dots(pair[0]), to_dots(pair[1]), contour.get_color());
+                        }
+                    }
+                }
+            },
+            // Vector fields without their arrow heads
+            Series::Quiver(quiver) => {
+                for [start, tip] in quiver.get_arrows() {
+                    canvas.line(to_dots(start), to_dots(tip), quiver.get_color());
+                }
+            },
+            Series::Stream(stream) => {
+                for line in stream.get_lines() {
+                    for pair in line.windows(2) {
+                        canvas.line(to_dots(pair[0]), to_dots(pair[1]), stream.get_color());
+                    }
+                }
+            },
+            // Areas are filled column by column between their two curves
+            Series::Fill(fill) => {
+                let color = fill.get_color();
+                for polygon in fill.get_polygons() {
+                    let half = polygon.len()/2;
+                    let (first, second) = (&polygon[..half], &polygon[half..]);
+                    for i in 0..half.saturating_sub(1) {
+                        let (a1, b1) = (to_dots(first[i]), to_dots(first[i+1]));
+                        let (a2, b2) = (to_dots(second[half-1-i]), to_dots(second[half-2-i]));
+                        if ![a1, b1, a2, b2].iter().all(|p| p.0.is_finite() && p.1.is_finite()) {
+                            continue;
+                        }
+                        let [a1, b1, a2, b2] = [a1, b1, a2, b2].map(|p| (p.0.round(), p.1.round()));
+                        // Only the columns and rows on the grid
+                        let left = a1.0.max(0.0) as i64;
+                        let right = b1.0.min((2*width) as f64) as i64;
+                        let bottom = (4*height) as f64;
+                        for x in left..=right {
+                            let t = if b1.0 == a1.0 {0.0} else {(x as f64-a1.0)/(b1.0-a1.0)};
+                            let y1 = a1.1+(b1.1-a1.1)*t;
+                            let y2 = a2.1+(b2.1-a2.1)*t;
+                            let top = y1.min(y2).round().max(0.0) as i64;
+                            for y in top..=(y1.max(y2).round().min(bottom) as i64) {
+                                canvas.dot(x, y, color);
+                            }
+                        }
+                    }
+                }
+            },
+            // Bars are filled with every dot inside them
+            Series::Bar(bar) => {
+                for (_, [x0, x1, y0, y1]) in bar_rects.iter().filter(|(k, _)| *k == nbars) {
+                    canvas.rect(to_dots((*x0, *y1)), to_dots((*x1, *y0)), bar.get_color());
+                }
+                nbars += 1;
+            },
+            Series::Hist(hist) => {
+                let color = hist.get_color();
+                if hist.get_hist_type() == HistType::Step {
+                    let points: Vec<(f64, f64)> = hist_outlines[nhists].iter().map(|&p| to_dots(p)).collect();
+                    for pair in points.windows(2) {
+                        canvas.line(pair[0], pair[1], color);
+                    }
+                } else {
+                    for (_, [x0, x1, y0, y1]) in hist_rects.iter().filter(|(k, _)| *k == nhists) {
+                        canvas.rect(to_dots((*x0, *y1)), to_dots((*x1, *y0)), color);
+                    }
+                }
+                nhists += 1;
+            },
+            Series::Box(boxes) => {
+                let color = boxes.get_color();
+                let half = boxes.get_width()*0.5;
+                for (i, stats) in boxes.get_stats().iter().enumerate().filter_map(|(i, s)| Some((i, s.as_ref()?))) {
+                    let x = i as f64;
+                    outline(&mut canvas, &boxes.get_outline(x, stats), color);
+                    canvas.line(to_dots((x-half, stats.median)), to_dots((x+half, stats.median)), color);
+                    canvas.line(to_dots((x, stats.q1)), to_dots((x, stats.whisker_low)), color);
+                    canvas.line(to_dots((x, stats.q3)), to_dots((x, stats.whisker_high)), color);
+                    for &outlier in stats.outliers.iter() {
+                        canvas.point(to_dots((x, outlier)), color);
+                    }
+                }
+            },
+            Series::Violin(violin) => {
+                for polygon in violin.get_polygons() {
+                    outline(&mut canvas, &polygon, violin.get_color());
+                }
+            },
+            // The bodies are as wide as a braille dot, the volume is left out
+            Series::Candlestick(candles) => {
+                for (i, x) in candles.get_positions().into_iter().enumerate() {
+                    let [open, high, low, close] = candles.get_prices(i);
+                    let color = candles.get_color(i);
+                    canvas.line(to_dots((x, high)), to_dots((x, low)), &color);
+                    let half = candles.get_width()*0.5;
+                    canvas.rect(to_dots((x-half, open)), to_dots((x+half, close)), &color);
+                }
+            },
+            Series::Line(line) => {
+                let color = line.get_color();
+                let points: Vec<(f64, f64)> = line.get_data().iter().map(|&p| to_dots(p)).collect();
+                let marker = line.get_style().marker;
+                // Error bars without caps, a band only by its edges
+                let band = line.get_band();
+                for (&(x, y), [x0, x1, y0, y1]) in line.get_data().iter().zip(line.get_error_ranges()) {
+                    if line.get_xerr().is_some() {
+                        canvas.line(to_dots((x0, y)), to_dots((x1, y)), color);
+                    }
+                    if line.get_yerr().is_some() && band.is_none() {
+                        canvas.line(to_dots((x, y0)), to_dots((x, y1)), color);
+                    }
+                }
+                if let Some(band) = band {
+                    let half = band.len()/2;
+                    for edge in [&band[..half], &band[half..]].iter() {
+                        for pair in edge.windows(2) {
+                            canvas.line(to_dots(pair[0]), to_dots(pair[1]), color);
+                        }
+                    }
+                }
+                for pair in line.get_line_points().windows(2) {
+                    canvas.line(to_dots(pair[0]), to_dots(pair[1]), color);
+                }
+                if let Some((_, stems)) = line.get_stems() {
+                    for [bottom, top] in stems {
+                        canvas.line(to_dots(bottom), to_dots(top), color);
+                    }
+                }
+                // A braille dot is too small to show the shape of a marker
+                if marker.is_some() || points.len() == 1 {
+                    for p in points.iter() {
+                        canvas.point(*p, color);
+                    }
+                }
+            },
+        }
+    }
+
+    let mut out = String::new();
+    let title = plot.get_title();
+    let title_start = (cols.saturating_sub(title.chars().count()))/2;
+    let ylabel = plot.get_ylabel();
+    let padding = title_start.saturating_sub(ylabel.chars().count()).max(1);
+    writeln!(out, "{}{}{}", ylabel, " ".repeat(padding), title).unwrap();
+
+    // Rows of the plot area where the y values go, from the bottom
+    let y_rows: Vec<usize> = (0..=y_ticks)
+        .map(|i| height-1-((i*(height-1)) as f64/y_ticks as f64).round() as usize)
+        .collect();
+    for row in 0..height {
+        match y_rows.iter().position(|&r| r == row) {
+            Some(i) => write!(out, "{:>width$}+", y_labels[i], width = margin-1).unwrap(),
+            None => write!(out, "{}|", " ".repeat(margin-1)).unwrap(),
+        }
+        canvas.row(row, &mut out);
+        out.push('\n');
+    }
+
+    // The x axis with its values centered below the ticks where they fit
+    let mut axis: Vec<char> = vec!['-'; width];
+    let mut labels: Vec<char> = vec![' '; width+margin+1];
+    let mut free_from = 0;
+    for i in 0..=x_ticks {
+        let column = ((i*(width-1)) as f64/x_ticks as f64).round() as usize;
+        // The first tick is the corner of the axes
+        if column > 0 {
+            axis[column] = '+';
+        }
+        let value = xlims[0]+i as f64*x_spacing;
+        let label: Vec<char> = plot.get_time_label(value, x_spacing).unwrap_or_else(|| tick_label(value, x_digits))
+            .chars().collect();
+        let start = (margin+column).saturating_sub(label.len()/2)
+            .min(labels.len().saturating_sub(label.len()));
+        if start >= free_from && start+label.len() <= labels.len() {
+            labels[start..start+label.len()].copy_from_slice(&label);
+            free_from = start+label.len()+1;
+        }
+    }
+    writeln!(out, "{}+{} {}", " ".repeat(margin-1), axis.iter().collect::<String>(), xlabel).unwrap();
+    writeln!(out, "{}", labels.iter().collect::<String>().trim_end()).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot::*;
+
+    #[test]
+    fn diagonal_line_is_drawn_with_braille_dots() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]));
+        let text = plot.to_terminal(40, 12);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 12);
+        // The line starts in the bottom left corner and ends in the top right one
+        assert!(!lines[1].ends_with('\u{2800}'));
+        assert!(lines[9].starts_with("0.00+") && !lines[9].starts_with("0.00+\u{2800}"));
+        assert!(lines[10].ends_with("+ x"));
+        assert!(lines[11].ends_with("1.00"));
+    }
+
+    #[test]
+    fn infinite_and_nan_values_are_left_out() {
+        let x = [0.0, 1.0, 2.0, 3.0];
+        let mut plot = Plot2D::fill_between(FillBetween::new(&x, &[0.0, f64::NEG_INFINITY, 1.0, 1.0], &[1.0; 4]));
+        plot.add_line(Line2D::new(&x, &[0.0, f64::INFINITY, f64::NAN, 1.0]));
+        plot.add_bar(Bar2D::new(&[f64::INFINITY, 1.0], &[1.0, f64::NAN]));
+        let text = plot.to_terminal(40, 12);
+        assert_eq!(text.lines().count(), 12);
+        // Points far outside of the grid are clipped instead of walked to
+        let mut canvas = super::Canvas::new(4, 2);
+        canvas.line((0.0, 0.0), (1e300, 1e300), &Color(0.0, 0.0, 0.0, 1.0));
+        canvas.line((-1e18, 7.0), (f64::MAX, 7.0), &Color(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(canvas.cells.iter().map(|c| c.0.count_ones()).sum::<u32>(), 8+8-1);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::plot::{Pie, Plot2D, Plot3D, PolarPlot};
+use crate::raster;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Snapshot tests for plots. A plot is rasterized headlessly and compared to a
+// reference PNG, so changes in how it renders show up as failing tests:
+//
+//     assert_plot_snapshot!(plot, "sine");
+//
+// The references are kept in tests/snapshots of the crate that runs the test.
+// A missing reference fails the test like a different one, so a typo in a name
+// or a reference left out of a commit can't pass unnoticed. Setting
+// PLOTTING_UPDATE_SNAPSHOTS writes new references and replaces the existing
+// ones after an intended change.
+
+// Size in pixels the plots are rendered at
+pub const SIZE: (u32, u32) = (640, 480);
+
+// Fraction of the pixels that may differ before the snapshot fails
+pub const TOLERANCE: f64 = 0.001;
+
+// Smallest color difference that is visible, as a fraction of the largest one
+const THRESHOLD: f64 = 0.1;
+
+// Plots that can be rendered for snapshots
+pub trait Snapshot {
+    fn render_snapshot(&self, width: u32, height: u32) -> Vec<u8>;
+}
+
+impl Snapshot for Plot2D {
+    fn render_snapshot(&self, width: u32, height: u32) -> Vec<u8> {
+        self.render_rgba(width, height)
+    }
+}
+
+impl Snapshot for Plot3D {
+    fn render_snapshot(&self, width: u32, height: u32) -> Vec<u8> {
+        self.render_rgba(width, height)
+    }
+}
+
+impl Snapshot for Pie {
+    fn render_snapshot(&self, width: u32, height: u32) -> Vec<u8> {
+        self.render_rgba(width, height)
+    }
+}
+
+impl Snapshot for PolarPlot {
+    fn render_snapshot(&self, width: u32, height: u32) -> Vec<u8> {
+        self.render_rgba(width, height)
+    }
+}
+
+// The YIQ color space separates brightness from hue, which makes the distance
+// between two colors closer to how different they look.
+fn yiq(pixel: &[u8]) -> (f64, f64, f64) {
+    // Blend with white so transparent pixels compare like they are shown
+    let alpha = pixel[3] as f64/255.0;
+    let blend = |c: u8| 255.0+(c as f64-255.0)*alpha;
+    let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+    (r*0.298_895_31+g*0.586_622_47+b*0.114_482_23,
+     r*0.595_977_99-g*0.274_176_10-b*0.321_801_89,
+     r*0.211_470_17-g*0.522_617_11+b*0.311_146_94)
+}
+
+fn color_delta(a: &[u8], b: &[u8]) -> f64 {
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    0.5053*(y1-y2).powi(2)+0.299*(i1-i2).powi(2)+0.1957*(q1-q2).powi(2)
+}
+
+// Compares two RGBA images of the same size. Returns the number of pixels that
+// look different together with an image where they are red on top of a faded
+// copy of the expected image.
+pub fn compare(actual: &[u8], expected: &[u8]) -> (usize, Vec<u8>) {
+    // The largest possible delta is the one between black and white
+    let max_delta = 35215.0*THRESHOLD*THRESHOLD;
+    let mut different = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+    for (a, e) in actual.chunks(4).zip(expected.chunks(4)) {
+        if color_delta(a, e) > max_delta {
+            different += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = (255.0-(255.0-yiq(e).0)*0.2) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+    (different, diff)
+}
+
+// Renders the plot and compares it to the reference `name`.png in `dir`. The
+// error explains what differs and where the new and diff images were written.
+pub fn check_snapshot<T: Snapshot, P: AsRef<Path>>(plot: &T, dir: P, name: &str,
+                                                    tolerance: f64) -> Result<(), String> {
+    let dir = dir.as_ref();
+    let (width, height) = SIZE;
+    let actual = plot.render_snapshot(width, height);
+    let reference = dir.join(format!("{}.png", name));
+    let new = dir.join(format!("{}.new.png", name));
+    let diff = dir.join(format!("{}.diff.png", name));
+    let save = |data: &[u8], path: &Path| {
+        fs::create_dir_all(dir)
+            .and_then(|_| raster::save_png(&raster::from_rgba(data, width, height), path))
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    };
+
+    if env::var_os("PLOTTING_UPDATE_SNAPSHOTS").is_some() {
+        return save(&actual, &reference);
+    }
+    if !reference.exists() {
+        save(&actual, &new)?;
+        return Err(format!("There is no snapshot {}, see {} and set PLOTTING_UPDATE_SNAPSHOTS to keep it",
+                           reference.display(), new.display()));
+    }
+    let expected = raster::load_png(&reference)
+        .map_err(|e| format!("Could not read {}: {}", reference.display(), e))?;
+    if (expected.width(), expected.height()) != (width, height) {
+        save(&actual, &new)?;
+        return Err(format!("The snapshot {} is {}x{} but the plot was rendered at {}x{}, see {}",
+                           reference.display(), expected.width(), expected.height(), width, height,
+                           new.display()));
+    }
+    let (different, diff_image) = compare(&actual, &raster::rgba(&expected));
+    let total = (width*height) as usize;
+    if different as f64 > tolerance*total as f64 {
+        save(&actual, &new)?;
+        save(&diff_image, &diff)?;
+        return Err(format!("The plot differs from the snapshot {} in {} of {} pixels, see {} and {}",
+                           reference.display(), different, total, new.display(), diff.display()));
+    }
+    // Leftovers from earlier failures are no longer relevant
+    let _ = fs::remove_file(&new);
+    let _ = fs::remove_file(&diff);
+    Ok(())
+}
+
+// Asserts that a Plot2D, Plot3D, Pie or PolarPlot looks like its stored
+// snapshot, see the testing module. An optional third argument overrides the
+// tolerance.
+#[macro_export]
+macro_rules! assert_plot_snapshot {
+    ($plot:expr, $name:expr) => {
+        $crate::assert_plot_snapshot!($plot, $name, $crate::testing::TOLERANCE)
+    };
+    ($plot:expr, $name:expr, $tolerance:expr) => {{
+        let dir = ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots");
+        if let Err(message) = $crate::testing::check_snapshot(&$plot, dir, $name, $tolerance) {
+            panic!("{}", message);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plot::*;
+
+    #[test]
+    fn snapshots_fail_with_a_diff_image() {
+        let dir = env::temp_dir().join(format!("plotting-snapshots-{}", std::process::id()));
+        let line = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[0.0, 1.0]));
+        // Without a reference the test fails and leaves the new image to keep
+        assert!(check_snapshot(&line, &dir, "line", TOLERANCE).is_err());
+        fs::rename(dir.join("line.new.png"), dir.join("line.png")).unwrap();
+        assert!(check_snapshot(&line, &dir, "line", TOLERANCE).is_ok());
+
+        let other = Plot2D::plot(Line2D::new(&[0.0, 1.0], &[1.0, 0.0]));
+        assert!(check_snapshot(&other, &dir, "line", TOLERANCE).is_err());
+        assert!(dir.join("line.diff.png").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
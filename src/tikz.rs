@@ -0,0 +1,299 @@
+use crate::plot::{Color, DrawStyle, HistType, Marker, Plot2D, Series, Step};
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+// Writes 2D plots as pgfplots code that LaTeX typesets with the fonts of the
+// document. The title and labels are copied as they are, so they can contain
+// math like $\alpha$, but characters such as % and & have to be escaped by the
+// caller.
+
+fn xcolor(color: &Color) -> String {
+    format!("{{rgb,1:red,{:.3};green,{:.3};blue,{:.3}}}", color.0, color.1, color.2)
+}
+
+fn color(color: &Color) -> String {
+    let mut options = format!("color={}", xcolor(color));
+    if color.3 < 1.0 {
+        write!(options, ", opacity={:.3}", color.3).unwrap();
+    }
+    options
+}
+
+// Positions of the ticks, the same ones the window draws
+fn ticks(lims: [f64;2], nvalues: usize) -> String {
+    let spacing = (lims[1]-lims[0])/nvalues as f64;
+    (0..=nvalues).map(|i| format!("{}", lims[0]+i as f64*spacing)).collect::<Vec<String>>().join(",")
+}
+
+pub fn render(plot: &Plot2D) -> String {
+    let axes = plot.get_axes();
+    let xlims = axes.get_axes().get_xaxes();
+    let ylims = axes.get_axes().get_yaxes();
+    let nvalues = axes.get_axes().get_nvalues().max(1);
+
+    let mut tikz = String::from("% Needs \\usepackage{pgfplots} in the preamble\n\\begin{tikzpicture}\n");
+    writeln!(tikz, "\\begin{{axis}}[\n    title={{{}}},\n    xlabel={{{}}},\n    ylabel={{{}}},",
+             plot.get_title(), plot.get_xlabel(), plot.get_ylabel()).unwrap();
+    writeln!(tikz, "    xmin={}, xmax={},\n    ymin={}, ymax={},", xlims[0], xlims[1], ylims[0],
+             ylims[1]).unwrap();
+    let mut xtick = ticks(xlims, nvalues);
+    let mut ytick = ticks(ylims, nvalues);
+    let mut names = String::new();
+    // A categorical axis gets a tick with the name of every category instead
+    if let Some((labels, horizontal)) = plot.get_categories() {
+        let (axis, tick) = if horizontal {("y", &mut ytick)} else {("x", &mut xtick)};
+        *tick = (0..labels.len()).map(|i| i.to_string()).collect::<Vec<String>>().join(",");
+        // Braces keep commas in the names from splitting them
+        let labels: Vec<String> = labels.iter().map(|l| format!("{{{}}}", l)).collect();
+        names = format!("    {}ticklabels={{{}}},\n", axis, labels.join(","));
+    }
+    writeln!(tikz, "    xtick={{{}}},\n    ytick={{{}}},", xtick, ytick).unwrap();
+    tikz.push_str(&names);
+    // The axes cross at the origin like in the window
+    tikz.push_str("    axis lines=middle,\n");
+    match axes.grid.as_str() {
+        "none" => (),
+        "both" => tikz.push_str("    grid=major,\n"),
+        _ => panic!("Not a valid string"),
+    }
+    tikz.push_str("]\n");
+
+    // A closed path through the points in axis coordinates
+    let polygon = |points: &[(f64, f64)]| {
+        let points: Vec<String> = points.iter().map(|(x, y)| format!("(axis cs:{},{})", x, y)).collect();
+        format!("{} -- cycle", points.join(" -- "))
+    };
+    let bar_rects = plot.get_bar_rects();
+    let hist_rects = plot.get_hist_rects();
+    let hist_outlines = plot.get_hist_outlines();
+    // In the order the series were added like in the window, bars and
+    // histograms need the index within their kind to find their rectangles
+    let (mut nbars, mut nhists) = (0, 0);
+    for series in plot.get_series() {
+        match series {
+            // The cells of heatmaps, their colorbars are left out
+            Series::Heatmap(heatmap) => {
+                let edges = heatmap.get_edges().map(|c| format!(", draw={}", xcolor(c))).unwrap_or_default();
+                for ([x0, x1, y0, y1], fill) in heatmap.get_cells() {
+                    writeln!(tikz, "\\filldraw[fill={}{}] (axis cs:{},{}) rectangle (axis cs:{},{});",
+                             xcolor(&fill), edges, x0, y0, x1, y1).unwrap();
+                }
+            },
+            Series::Contour(contour) => {
+                if contour.is_filled() {
+                    for (fill, polygons) in contour.get_bands() {
+                        for band in polygons {
+                            writeln!(tikz, "\\fill[{}] {};", color(&fill), polygon(&band)).unwrap();
+                        }
+                    }
+                    continue;
+                }
+                for (level, lines) in contour.get_lines() {
+                    for line in lines {
+                        let points: Vec<String> = line.iter().map(|(x, y)| format!("(axis cs:{},{})", x, y)).collect();
+                        write!(tikz, "\\draw[{}] {}", color(contour.get_color()), points.join(" -- ")).unwrap();
+                        // pgfplots puts the label on the middle of the path
+                        if contour.has_labels() {
+                            write!(tikz, " node[pos=0.5, sloped, fill=white, inner sep=1pt, font=\\tiny] {{{}}}", contour.get_label(level)).unwrap();
+                        }
+                        tikz.push_str(";\n");
+                    }
+                }
+            },
+            Series::Quiver(quiver) => {
+                for [(x0, y0), (x1, y1)] in quiver.get_arrows() {
+                    writeln!(tikz, "\\draw[{}, ->] (axis cs:{},{}) -- (axis cs:{},{});", color(quiver.get_color()),
+                             x0, y0, x1, y1).unwrap();
+                }
+            },
+            Series::Stream(stream) => {
+                for line in stream.get_lines() {
+                    // The arrow tip is put on the first half of the line to show the direction
+                    let middle = line.len()/2;
+                    let part = |points: &[(f64, f64)]| {
+                        points.iter().map(|(x, y)| format!("(axis cs:{},{})", x, y)).collect::<Vec<String>>().join(" -- ")
+                    };
+                    writeln!(tikz, "\\draw[{}, ->] {};", color(stream.get_color()), part(&line[..=middle])).unwrap();
+                    writeln!(tikz, "\\draw[{}] {};", color(stream.get_color()), part(&line[middle..])).unwrap();
+                }
+            },
+            Series::Fill(fill) => {
+                for area in fill.get_polygons() {
+                    writeln!(tikz, "\\fill[{}] {};", color(&fill.get_fill_color()), polygon(&area)).unwrap();
+                }
+            },
+            Series::Bar(bar) => {
+                for (_, [x0, x1, y0, y1]) in bar_rects.iter().filter(|(k, _)| *k == nbars) {
+                    writeln!(tikz, "\\fill[{}] (axis cs:{},{}) rectangle (axis cs:{},{});",
+                             color(bar.get_color()), x0, y0, x1, y1).unwrap();
+                }
+                nbars += 1;
+            },
+            Series::Hist(hist) => {
+                if hist.get_hist_type() == HistType::Step {
+                    writeln!(tikz, "\\addplot[{}, mark=none] coordinates {{", color(hist.get_color())).unwrap();
+                    for (x, y) in hist_outlines[nhists].iter() {
+                        writeln!(tikz, "    ({}, {})", x, y).unwrap();
+                    }
+                    tikz.push_str("};\n");
+                    if let Some(legend) = hist.get_legend() {
+                        writeln!(tikz, "\\addlegendentry{{{}}}", legend).unwrap();
+                    }
+                } else {
+                    for (_, [x0, x1, y0, y1]) in hist_rects.iter().filter(|(k, _)| *k == nhists) {
+                        writeln!(tikz, "\\fill[{}] (axis cs:{},{}) rectangle (axis cs:{},{});",
+                                 color(hist.get_color()), x0, y0, x1, y1).unwrap();
+                    }
+                }
+                nhists += 1;
+            },
+            Series::Box(boxes) => {
+                let style = format!("{}, fill opacity=0.3", color(boxes.get_color()));
+                for (i, stats) in boxes.get_stats().iter().enumerate().filter_map(|(i, s)| Some((i, s.as_ref()?))) {
+                    let x = i as f64;
+                    let cap = boxes.get_width()*0.25;
+                    writeln!(tikz, "\\filldraw[{}] {};", style, polygon(&boxes.get_outline(x, stats))).unwrap();
+                    writeln!(tikz, "\\draw[{}] (axis cs:{},{}) -- (axis cs:{},{}) (axis cs:{},{}) -- (axis cs:{},{});",
+                             color(boxes.get_color()), x, stats.q1, x, stats.whisker_low, x, stats.q3, x,
+                             stats.whisker_high).unwrap();
+                    for whisker in [stats.whisker_low, stats.whisker_high].iter() {
+                        writeln!(tikz, "\\draw[{}] (axis cs:{},{}) -- (axis cs:{},{});", color(boxes.get_color()),
+                                 x-cap, whisker, x+cap, whisker).unwrap();
+                    }
+                    let half = boxes.get_width()*if boxes.is_notched() {0.25} else {0.5};
+                    writeln!(tikz, "\\draw[thick] (axis cs:{},{}) -- (axis cs:{},{});", x-half, stats.median,
+                             x+half, stats.median).unwrap();
+                    for outlier in stats.outliers.iter() {
+                        writeln!(tikz, "\\draw[{}] (axis cs:{},{}) circle[radius=2.25pt];",
+                                 color(boxes.get_color()), x, outlier).unwrap();
+                    }
+                }
+            },
+            Series::Violin(violin) => {
+                for outline in violin.get_polygons() {
+                    writeln!(tikz, "\\filldraw[{}, fill opacity=0.5] {};", color(violin.get_color()),
+                             polygon(&outline)).unwrap();
+                }
+                for (i, median) in violin.get_medians().into_iter().enumerate().filter_map(|(i, m)| Some((i, m?))) {
+                    writeln!(tikz, "\\fill (axis cs:{},{}) circle[radius=2.25pt];", i, median).unwrap();
+                }
+            },
+            // The volume bars are left out
+            Series::Candlestick(candles) => {
+                let half = candles.get_width()*0.5;
+                for (i, x) in candles.get_positions().into_iter().enumerate() {
+                    let [open, high, low, close] = candles.get_prices(i);
+                    let style = color(&candles.get_color(i));
+                    writeln!(tikz, "\\draw[{}] (axis cs:{},{}) -- (axis cs:{},{});", style, x, low, x, high).unwrap();
+                    writeln!(tikz, "\\filldraw[{}] (axis cs:{},{}) rectangle (axis cs:{},{});", style, x-half, open,
+                             x+half, close).unwrap();
+                }
+            },
+            Series::Line(line) => {
+                let line_style = line.get_style();
+                if let Some((baseline, stems)) = line.get_stems() {
+                    let xs = line.get_data().iter().map(|p| p.0).filter(|x| x.is_finite());
+                    let (left, right) = xs.fold((f64::MAX, f64::MIN), |(l, r), x| (l.min(x), r.max(x)));
+                    write!(tikz, "\\draw[{}]", color(&line.get_line_color())).unwrap();
+                    for [(x, y0), (_, y1)] in stems {
+                        write!(tikz, " (axis cs:{},{}) -- (axis cs:{},{})", x, y0, x, y1).unwrap();
+                    }
+                    writeln!(tikz, " (axis cs:{},{}) -- (axis cs:{},{});", left, baseline, right, baseline).unwrap();
+                }
+                if let Some(band) = line.get_band() {
+                    writeln!(tikz, "\\fill[{}] {};", color(&line.get_line_color().scale_alpha(0.25)),
+                             polygon(&band)).unwrap();
+                }
+                let mut style = color(&line.get_line_color());
+                // Error bars are given explicitly as offsets after the coordinates
+                let xerr = line.get_xerr().is_some();
+                let yerr = line.get_yerr().is_some() && line.get_band().is_none();
+                // pgfplots names the steps by where the mark is on the constant part
+                match line_style.draw {
+                    DrawStyle::Line => (),
+                    DrawStyle::Steps(Step::Pre) => style.push_str(", const plot mark right"),
+                    DrawStyle::Steps(Step::Post) => style.push_str(", const plot mark left"),
+                    DrawStyle::Steps(Step::Mid) => style.push_str(", const plot mark mid"),
+                    DrawStyle::None | DrawStyle::Stem(_) => style.push_str(", only marks"),
+                }
+                match line_style.marker {
+                    Some(marker) => {
+                        let (name, size) = match marker {
+                            Marker::Point => ("*", line.get_marker_size()/6.0),
+                            Marker::Circle => ("*", line.get_marker_size()*0.5),
+                            Marker::Square => ("square*", line.get_marker_size()*0.5),
+                            Marker::Triangle => ("triangle*", line.get_marker_size()*0.5),
+                            Marker::Diamond => ("diamond*", line.get_marker_size()*0.5),
+                            Marker::Cross => ("x", line.get_marker_size()*0.5),
+                            Marker::Plus => ("+", line.get_marker_size()*0.5),
+                            Marker::Star => ("star", line.get_marker_size()*0.5),
+                        };
+                        // The sizes are radii in pixels while pgfplots wants points
+                        write!(style, ", mark={}, mark size={:.2}pt, mark options={{solid, fill={}, draw={}",
+                               name, size*0.75, xcolor(&line.get_face_color()),
+                               xcolor(&line.get_edge_color())).unwrap();
+                        for (name, color) in [("fill", line.get_face_color()), ("draw", line.get_edge_color())].iter() {
+                            if color.3 < 1.0 {
+                                write!(style, ", {} opacity={:.3}", name, color.3).unwrap();
+                            }
+                        }
+                        style.push('}');
+                    },
+                    None => style.push_str(", mark=none"),
+                }
+                if xerr || yerr {
+                    write!(style, ", error bars/.cd, x dir={}, x explicit, y dir={}, y explicit, \
+                                   error mark options={{solid, mark size={:.2}pt}}",
+                           if xerr {"both"} else {"none"}, if yerr {"both"} else {"none"},
+                           line.get_cap_size()*0.5*0.75).unwrap();
+                }
+                writeln!(tikz, "\\addplot[{}] coordinates {{", style).unwrap();
+                for (&(x, y), [x0, x1, y0, y1]) in line.get_data().iter().zip(line.get_error_ranges()) {
+                    if !(x.is_finite() && y.is_finite()) {
+                        continue;
+                    }
+                    if xerr || yerr {
+                        writeln!(tikz, "    ({}, {}) += ({}, {}) -= ({}, {})", x, y, x1-x, y1-y, x-x0, y-y0).unwrap();
+                    } else {
+                        writeln!(tikz, "    ({}, {})", x, y).unwrap();
+                    }
+                }
+                tikz.push_str("};\n");
+                if let Some(legend) = line.get_legend() {
+                    writeln!(tikz, "\\addlegendentry{{{}}}", legend).unwrap();
+                }
+            },
+        }
+    }
+    tikz.push_str("\\end{axis}\n\\end{tikzpicture}\n");
+    tikz
+}
+
+pub fn save<P: AsRef<std::path::Path>>(plot: &Plot2D, path: P) -> io::Result<()> {
+    fs::write(path, render(plot))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot::*;
+
+    #[test]
+    fn tikz_has_limits_and_coordinates() {
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 2.0], &[1.0, 3.0]).color(Color::RED)
+                                .marker(Marker::Point).draw(DrawStyle::None))
+            .xlabel("$t$");
+        let tikz = plot.to_tikz_string();
+        assert!(tikz.contains("xlabel={$t$}"));
+        assert!(tikz.contains("xmin=0, xmax=2,\n    ymin=1, ymax=3,"));
+        assert!(tikz.contains("\\addplot[color={rgb,1:red,1.000;green,0.000;blue,0.000}, only marks"));
+        assert!(tikz.contains("    (2, 3)\n};"));
+        let tikz = Plot2D::bar(Bar2D::categories(&["a", "b, c"], &[1.0, 2.0])).to_tikz_string();
+        assert!(tikz.contains("    xtick={0,1},\n") && tikz.contains("    xticklabels={{a},{b, c}},\n"));
+        // Transparent markers keep their opacity
+        let plot = Plot2D::plot(Line2D::new(&[0.0, 2.0], &[1.0, 3.0]).marker(Marker::Circle)
+                                .draw(DrawStyle::None).alpha(0.5));
+        assert!(plot.to_tikz_string().contains("fill opacity=0.500, draw opacity=0.500}"));
+    }
+}
@@ -8,30 +8,76 @@ use iced::{
 
 
 use iced::{
-    canvas::{self, Cursor, path, Path, Text, Stroke, Fill, LineJoin, LineCap},
-    executor, window, Application, Canvas, Color, Command, Element,
-    Length, Point, Rectangle, Settings, Size, Subscription, Vector, HorizontalAlignment,
+    canvas::{self, Cursor, path, Path, Text, Stroke, LineJoin, LineCap},
+    executor, window, Application, Canvas, Color, Command, Element, Font, Settings,
+    Length, Point, Rectangle, Size, HorizontalAlignment,
     VerticalAlignment, Row, button, Button,
 };
 
-use std::cmp::Ordering;
+use std::cell::Cell;
+use std::path::PathBuf;
+
+
+use crate::plot::{Plot2D, Pie, PolarPlot};
+use crate::scene::{self, Scene, Primitive, Segment, HAlign, VAlign, Rect};
+use crate::font;
+
+// The kinds of plots the window can show
+pub enum Shown {
+    Plot(Plot2D),
+    Pie(Pie),
+    Polar(PolarPlot),
+}
 
+impl Shown {
 
-use crate::plot::*;
+    fn scene(&self, width: f32, height: f32) -> Scene {
+        match self {
+            Shown::Plot(plot) => plot.scene(width, height),
+            Shown::Pie(pie) => pie.scene(width, height),
+            Shown::Polar(plot) => plot.scene(width, height),
+        }
+    }
+
+    fn save_png(&self, path: &PathBuf, width: u32, height: u32) -> std::io::Result<()> {
+        match self {
+            Shown::Plot(plot) => plot.save_png(path, width, height),
+            Shown::Pie(pie) => pie.save_png(path, width, height),
+            Shown::Polar(plot) => plot.save_png(path, width, height),
+        }
+    }
+
+}
 
 pub struct Window {
    plot: Plotting,
    button_state: button::State,
 }
 
+impl Window {
+
+    // Opens the plot in a window, blocks until it is closed
+    pub fn open(plot: Shown) {
+        Window::run(Settings{
+            window: window::Settings::default(),
+            flags: plot,
+            default_font: None,
+            default_text_size: 20,
+            antialiasing: true,
+        }).unwrap();
+    }
+
+}
+
 pub struct Plotting {
     state: State,
 }
 
 struct State {
-    plot_background: canvas::Cache,
-    lines: canvas::Cache,
-    plot: Plot2D,
+    plot_cache: canvas::Cache,
+    plot: Shown,
+    // Size of the canvas the last time it was drawn. Used when saving the plot.
+    size: Cell<Size>,
 
 }
 
@@ -45,29 +91,31 @@ impl State {
             .into()
     }
     
-    pub fn new(plot: Plot2D) -> Self {
+    pub fn new(plot: Shown) -> Self {
         Self {
-        plot_background: Default::default(),
-        lines: Default::default(),
+        plot_cache: Default::default(),
         plot,
+        size: Cell::new(Size::new(800.0, 600.0)),
         }
         
     }
 
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Message {
     PlotSaved,
+    // The file picked in the save dialog, None if it was cancelled
+    SaveTo(Option<PathBuf>),
 }
 
 
 impl Application for Window {
     type Message = Message;
     type Executor = executor::Default;
-    type Flags = Plot2D;
+    type Flags = Shown;
 
-    fn new(_flags: Plot2D) -> (Self, Command<Self::Message>) {
+    fn new(_flags: Shown) -> (Self, Command<Self::Message>) {
         (
             Self {
                 plot: Plotting {
@@ -86,9 +134,22 @@ impl Application for Window {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::PlotSaved => {
-                ()
+                // Ask where to save the plot without blocking the window
+                let dialog = rfd::AsyncFileDialog::new()
+                    .add_filter("PNG", &["png"])
+                    .set_file_name("plot.png")
+                    .save_file();
+                return Command::perform(dialog, |file| Message::SaveTo(file.map(|f| f.path().to_path_buf())));
+            },
+            Message::SaveTo(Some(path)) => {
+                // The plot is rasterized at the size it has on screen
+                let state = &self.plot.state;
+                let size = state.size.get();
+                if let Err(e) = state.plot.save_png(&path, size.width as u32, size.height as u32) {
+                    eprintln!("Could not save the plot to {}: {}", path.display(), e);
+                }
             },
-            _ => (),
+            Message::SaveTo(None) => (),
         }
         Command::none()
     }
@@ -117,300 +178,80 @@ impl<Message> canvas::Program<Message> for State {
         _cursor: Cursor,
         ) -> Vec<canvas::Geometry> {
 
-        let xlims = self.plot.get_axes().get_axes().get_xaxes();
-        let ylims = self.plot.get_axes().get_axes().get_yaxes();
-
-        let edge: usize = 30;
-
-
-        let background = self.plot_background.draw(bounds.size(), |frame| {
-
-            let x_grid = Linspace::linspace(xlims[0], xlims[1], frame.width() as usize-2*edge);
-            let y_grid = Linspace::linspace(ylims[0], ylims[1], frame.height() as usize-2*edge);
-            let x_grid_window: Vec<(f64, f32)> = x_grid.iter().cloned().
-                zip((edge..(frame.width() as usize - edge+1)).map(|x| x as f32)).collect();
-//            let x_origin = x_grid_window.iter().
-//                filter(|(a, _)| a.min((xlims[0].abs()+xlims[1].abs())/frame.width() as f64)==*a).
-//                map(|(_, b)| *b).next().unwrap();
-
-            let y_grid_window: Vec<(f64, f32)> = y_grid.iter().cloned().
-                zip((edge..(frame.height() as usize-edge+1)).rev().map(|x| x as f32)).collect();
-//            let y_origin = y_grid_window.iter().
-//                filter(|(a, _)| a.min((ylims[0].abs()+ylims[1].abs())/frame.height() as f64)==*a).
-//                map(|(_, b)| *b).next().unwrap();
-
-
-            let y_origin = y_grid_window.iter().min_by(|(a,_), (b,_)| {
-                if a.abs()<b.abs() {
-                    if(a==b) { return Ordering::Equal }
-                    return Ordering::Less
-                }
-                Ordering::Greater
-            }).unwrap().1;
-            let x_origin = x_grid_window.iter().min_by(|(a,_), (b,_)| {
-                 if a.abs()<b.abs() {
-                    if(a==b) { return Ordering::Equal }
-                    return Ordering::Less
-                }
-                Ordering::Greater               
-            }).unwrap().1;
-
-
-            // Draw the x axes
-            let mut x_axes = path::Builder::new();
-            let center = frame.center();
-            let right_center = Point::new(frame.width()-edge as f32, y_origin);
-            let left_center = Point::new(edge as f32, y_origin);
-            x_axes.move_to(left_center);
-            x_axes.line_to(right_center);
-            x_axes.line_to(right_center+Vector::from([-1.0, -1.0]));
-            x_axes.move_to(right_center);
-            x_axes.line_to(right_center+Vector::from([-1.0, 1.0]));
-            let p = x_axes.build();
-            // Draw the y axes
-            let mut y_axes = path::Builder::new();
-            let upper_center = Point::new(x_origin, edge as f32);
-            let lower_center = Point::new(x_origin, frame.height()-edge as f32);
-            y_axes.move_to(lower_center);
-            y_axes.line_to(upper_center);
-            y_axes.line_to(upper_center+Vector::from([-1.0, 1.0]));
-            y_axes.move_to(upper_center);
-            y_axes.line_to(upper_center+Vector::from([1.0, 1.0]));
-            let p2 = y_axes.build();
-            
-            frame.fill(&p, Color::BLACK);
-            frame.stroke(&p, Stroke{color: Color::BLACK, width: 2.0, line_cap: LineCap::Butt
-                , line_join: LineJoin::Miter});
-
-            frame.stroke(&p2, Stroke{color: Color::BLACK, width: 2.0, line_cap: LineCap::Butt
-                , line_join: LineJoin::Miter});
-            
-            // Build the grid depending on plot.
-            let mut x_grid = path::Builder::new();
-            let mut y_grid = path::Builder::new();
-            let grid = self.plot.get_axes();
-            let nbr_of_x_points = grid.get_axes().get_nvalues();
-            let nbr_of_y_points = nbr_of_x_points;
-            let x_spacing = (xlims[1]-xlims[0])/(nbr_of_x_points as f64);
-            let y_spacing = (ylims[1]-ylims[0])/(nbr_of_y_points as f64);
-            let x_step = (frame.width()-(2*edge) as f32) as f64/(nbr_of_x_points as f64);
-            let y_step = (frame.height()-(2*edge) as f32) as f64/(nbr_of_y_points as f64);
-
-
-            let mut nbr_of_x_digits = 0;
-            let mut nbr_of_y_digits = 0;
-            for i in 0..std::f32::DIGITS {
-                let temp_x = x_spacing.fract()*(10.0_f64.powi(i as i32));
-                let diff_x = temp_x.fract();
-
-                if(diff_x < std::f64::EPSILON) {
-                    break;
-                }
-                nbr_of_x_digits +=1;
-            }
-            for i in 0..std::f32::DIGITS {
-                let temp_y = y_spacing.fract()*(10.0_f64.powi(i as i32));
-                let diff_y = temp_y.fract();
-                if(diff_y < std::f64::EPSILON) {
-                    break;
-                }
-                nbr_of_y_digits +=1;
-            }
-
-            // Initialize variables for the axes texts
-            let mut x_text_val = xlims[0];
-            let mut y_text_val = ylims[0];
-            let mut x_text: Text = Text::from(format!("{:.ndigits$}", x_text_val,
-                                                      ndigits=nbr_of_x_digits));
-            let mut y_text: Text = Text::from("");
-            // Draw the text for the first x value
-            x_text.position = Point::new(x_grid_window[0].1+2.0, y_origin+5.0);
-            x_text.horizontal_alignment = HorizontalAlignment::Left;
-            frame.fill_text(x_text);
-
-//            let mut origin_text = Text::from(format!("{:.ndigits$}", 0.0, ndigits = nbr_of_x_digits));
-//            origin_text.position = Point::new(x_origin, y_origin+5.0);
-//            origin_text.horizontal_alignment = HorizontalAlignment::Center;
-//            frame.fill_text(origin_text);
-
-            // Draw the grid depending on what type of grid the plot uses.
-            match grid.grid.as_str() {
-                "none" => {
-                    let mut x_pos: f32 = x_grid_window[0].1;
-                    let mut y_pos: f32 = y_grid_window[0].1;
-                    while(x_pos < frame.width()-(edge+1) as f32) {
-                        x_grid.move_to(Point::new(x_pos,y_origin-3.0));
-                        x_grid.line_to(Point::new(x_pos, y_origin+3.0));
-                        x_pos+=x_step as f32;
-                        x_text_val += x_spacing;
-                        x_text = Text::from(format!("{:.ndigits$}", x_text_val, ndigits = nbr_of_x_digits));
-                        println!("x_text_val is {}", x_text_val);
-                        x_text.position = Point::new(x_pos, y_origin+5.0);
-                        x_text.horizontal_alignment = HorizontalAlignment::Center;
-                        if((x_pos-x_origin).abs()>0.1*x_step as f32) {
-                            if((x_origin-y_origin).abs()>0.01) {
-                                frame.fill_text(x_text);
-                            }
-
-                        }
-
-                    }
-
-                    while(y_pos > edge as f32) {
-                        y_grid.move_to(Point::new(x_origin-3.0, y_pos));
-                        y_grid.line_to(Point::new(x_origin+3.0, y_pos));
-                        y_text = Text::from(format!("{:.ndigits$}", y_text_val, ndigits = nbr_of_y_digits));
-                        y_text_val += y_spacing;
-                        y_text.position = Point::new(x_origin-10.0, y_pos);
-                        y_text.vertical_alignment = VerticalAlignment::Center;
-                        y_text.horizontal_alignment = HorizontalAlignment::Right;
-                        if((y_pos-y_origin).abs()>0.1*y_step as f32) {
-                            if((x_origin-y_origin).abs()>0.01) {
-                                frame.fill_text(y_text);                           
-                            }
-
-                        }
-                        y_pos-=y_step as f32;
-
-
-
-                    }
-
-
-
-                },
-                "both" => {
-                    let mut x_pos: f32 = x_grid_window[0].1;
-                    let mut y_pos: f32 = y_grid_window[0].1;
-                    while(x_pos < frame.width()+x_step as f32-edge as f32) {
-                        x_grid.move_to(Point::new(x_pos,edge as f32));
-                        x_grid.line_to(Point::new(x_pos, frame.height()-edge as f32));
-                        x_text_val += x_spacing;
-                        x_text = Text::from(format!("{:.ndigits$}", x_text_val, ndigits = nbr_of_x_digits));
-                        x_text.horizontal_alignment = HorizontalAlignment::Left;
-                        x_pos+=x_step as f32;
-                        x_text.position = Point::new(x_pos+2.0, y_origin+5.0);
-                        if((x_pos-x_origin).abs()>0.1*x_step as f32) {
-                            if((x_origin-y_origin).abs()>0.01) {
-                                frame.fill_text(x_text);
-                            }
-
-                        }
-                    }
-
-                    while(y_pos > edge as f32-x_step as f32) {
-                        y_grid.move_to(Point::new(edge as f32, y_pos));
-                        y_grid.line_to(Point::new(frame.width()-edge as f32, y_pos));
-                        y_text = Text::from(format!("{:.ndigits$}", y_text_val, ndigits = nbr_of_y_digits));
-                        y_text_val += y_spacing;
-                        y_text.position = Point::new(x_origin-10.0, y_pos+2.0);
-                        y_text.vertical_alignment = VerticalAlignment::Bottom;
-                        y_text.horizontal_alignment = HorizontalAlignment::Right;
-                        if((y_pos-y_origin).abs()>0.1*y_step as f32) {
-                            if((x_origin-y_origin).abs()>0.01) {
-                                frame.fill_text(y_text);                           
-                            }
-
-                        }
-                        y_pos-=y_step as f32;
-                    }                   
-                },
-                _ => panic!("Not a valid string")
-            };
-            // Draw the text for the last y value
-            // Build the grid and draw it.
-            let x_grid = x_grid.build();
-            let y_grid = y_grid.build();
-            //frame.stroke(&p, Stroke{color: Color::BLACK, width: 2.0, line_cap: LineCap::Butt,
-              //  line_join: LineJoin::Miter});
-            frame.stroke(&x_grid, Stroke{color: Color::BLACK, width: 1.0, line_cap: LineCap::Butt,
-                line_join: LineJoin::Miter});
-            frame.stroke(&y_grid, Stroke{color: Color::BLACK, width: 1.0, line_cap: LineCap::Butt,
-                line_join: LineJoin::Miter});
-
-
-
+        self.size.set(bounds.size());
+        let plot = self.plot_cache.draw(bounds.size(), |frame| {
+            let scene = self.plot.scene(frame.width(), frame.height());
+            draw_scene(frame, &scene);
         });
-        // Draw the actual plot 
-        let _lines = self.lines.draw(bounds.size(), |frame| {
-            let x_grid = Linspace::linspace(xlims[0], xlims[1], ((frame.width()-2.0*edge as f32)*4.0) as usize);
-            let y_grid = Linspace::linspace(ylims[0], ylims[1], ((frame.height()-2.0*edge as f32)*4.0) as usize);
-
-            let x_grid_window: Vec<(f64, f32)> = x_grid.iter().cloned().
-                zip((4*edge..((frame.width()*4.0) as usize - 4*edge+1)).map(|x| x as f32*0.25)).collect();
-            let y_grid_window: Vec<(f64, f32)> = y_grid.iter().cloned().
-                zip((4*edge..((frame.height()*4.0) as usize-4*edge+1)).rev().map(|x| x as f32*0.25)).collect();
-            for line in self.plot.get_lines() {
-                 let mut line_draw  = path::Builder::new();
-                 let min_y_vals: Vec<f64> = y_grid.iter().map(|x| (x-line.data[0].1).abs()).collect();
-                 let min_x_vals: Vec<f64> = x_grid.iter().map(|x| (x-line.data[0].0).abs()).collect();
-                 let (_, ind_y) = min(&min_y_vals);
-                 let (_, ind_x) = min(&min_x_vals);
-                 let start_y = y_grid_window[ind_y].1;
-                 let start_x = x_grid_window[ind_x].1;
-//                 let start_y = y_grid_window.iter().find(|(a,_)| *a==line.data[0].1).unwrap().1;
-                 line_draw.move_to(Point::new(start_x, start_y));
-                 for (x,y) in line.get_data().iter() {
-//                 line.get_data().iter().map(|(x,y)| {
-                    let x_coord = x_grid_window.iter().min_by(|&&x_1, &&x_2| {
-                        let diff = (x_1.0-x).abs()-(x_2.0-x).abs();
-                        if diff.is_sign_positive() {
-                            if diff==0.0 {
-                                return Ordering::Equal
-                            }
-                            Ordering::Greater
-                        } else {
-                            Ordering::Less
-                        }
-
-                    }).unwrap().1;
-                    let y_coord = y_grid_window.iter().min_by(|&&y_1, &&y_2| {
-                        let diff = (y_1.0-y).abs()-(y_2.0-y).abs();
-                        if diff.is_sign_positive() {
-                            if diff==0.0 {
-                                return Ordering::Equal
-                            }
-                            Ordering::Greater
-                        } else {
-                            Ordering::Less
-                        }
-
-                    }).unwrap().1;
-
-                    let new_point = Point::new(x_coord, y_coord);
-                    match &line.linestyle[0..] {
-                        "-" => line_draw.line_to(new_point),
-                        "." => {
-                           line_draw.circle(new_point, 2f32); 
-                        },
-                        _ => panic!("Not a valid linestyle"),
-                    }
 
-                }
-                let p = line_draw.build();
-                let line_color = line.get_color();
-                let color = iced::Color::new(line_color.0, line_color.1, line_color.2, line_color.3);
-                //frame.fill(&p, Color::BLACK);
-                match &line.linestyle[0..] {
-                    "-" => frame.stroke(&p, Stroke{color, width: 2.0, line_cap: LineCap::Butt,
-                    line_join: LineJoin::Miter}),
-                    "." => {
-                       frame.fill(&p, color); 
-                    },
-                    _ => panic!("Not a valid linestyle"),
-                }
+        vec![plot]
+    }
 
-            }
-            
-        });
+}
 
-        vec![background, _lines]
-        
-    
+fn build_path(path: &scene::Path) -> Path {
+    let mut builder = path::Builder::new();
+    for segment in path.segments.iter() {
+        match *segment {
+            Segment::MoveTo(x, y) => builder.move_to(Point::new(x, y)),
+            Segment::LineTo(x, y) => builder.line_to(Point::new(x, y)),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => builder.bezier_curve_to(Point::new(x1, y1),
+                                                                              Point::new(x2, y2),
+                                                                              Point::new(x, y)),
+            Segment::Close => builder.close(),
+        }
     }
-    
-
+    builder.build()
 }
 
+fn to_color(color: &crate::plot::Color) -> Color {
+    Color::new(color.0, color.1, color.2, color.3)
+}
 
+// Draws a scene on an iced frame. The canvas can't clip, so the paths in a clip
+// region are cut to its rectangle before they are drawn.
+pub(crate) fn draw_scene(frame: &mut canvas::Frame, scene: &Scene) {
+    draw_primitives(frame, &scene.primitives, None);
+}
 
+fn draw_primitives(frame: &mut canvas::Frame, primitives: &[Primitive], clip: Option<Rect>) {
+    for primitive in primitives {
+        match primitive {
+            Primitive::Stroke{path, color, width} => {
+                let path = clip.map_or_else(|| path.clone(), |rect| rect.clip_stroke(path));
+                frame.stroke(&build_path(&path), Stroke{color: to_color(color), width: *width,
+                    line_cap: LineCap::Butt, line_join: LineJoin::Miter});
+            },
+            Primitive::Fill{path, color} => {
+                let path = clip.map_or_else(|| path.clone(), |rect| rect.clip_fill(path));
+                frame.fill(&build_path(&path), to_color(color));
+            },
+            Primitive::Text(text) if clip.is_some_and(|rect| !rect.contains(text.position)) => (),
+            Primitive::Text(text) => {
+                frame.fill_text(Text {
+                    content: text.content.clone(),
+                    position: Point::new(text.position.0, text.position.1),
+                    color: to_color(&text.color),
+                    size: text.size,
+                    // Use the same font as the exported files
+                    font: Font::External{name: "DejaVu Sans", bytes: font::FONT_DATA},
+                    horizontal_alignment: match text.horizontal_alignment {
+                        HAlign::Left => HorizontalAlignment::Left,
+                        HAlign::Center => HorizontalAlignment::Center,
+                        HAlign::Right => HorizontalAlignment::Right,
+                    },
+                    vertical_alignment: match text.vertical_alignment {
+                        VAlign::Top => VerticalAlignment::Top,
+                        VAlign::Center => VerticalAlignment::Center,
+                        VAlign::Bottom => VerticalAlignment::Bottom,
+                    },
+                });
+            },
+            Primitive::Clip{rect, primitives} => {
+                let rect = clip.map_or(*rect, |outer| outer.intersect(rect));
+                draw_primitives(frame, primitives, Some(rect))
+            },
+            Primitive::Group{primitives, ..} => draw_primitives(frame, primitives, clip),
+        }
+    }
+}